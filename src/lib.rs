@@ -2,30 +2,176 @@
 #![allow(unused_mut)]
 
 pub mod util {
+    pub mod adapter;
     pub mod camera;
+    pub mod camera_path;
+    pub mod cli;
+    pub mod clock;
     pub mod constructors;
+    pub mod cpu_march;
+    pub mod frame_stream;
+    pub mod gizmo;
+    pub mod history;
     pub mod image;
+    pub mod input;
+    pub mod inspector;
+    pub mod offscreen;
+    pub mod overlay;
+    pub mod recorder;
+    pub mod renderer;
+    pub mod scene;
+    pub mod screenshot;
     pub mod shapes;
+    pub mod terminal;
     pub mod vertex;
+    pub mod video_wall;
 }
 
-use cgmath::{Deg, Quaternion, Rotation3, SquareMatrix};
+use cgmath::{Deg, EuclideanSpace};
 use std::default::Default;
-use wgpu::{BufferBindingType, DynamicOffset};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use winit::{
+    dpi::PhysicalPosition,
     event::*,
-    event_loop::{ControlFlow, EventLoop},
+    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
     window::{Window, WindowBuilder},
 };
 
-use wgpu::util::DeviceExt;
-
+use crate::util::adapter::{self, AdapterOptions, GpuError};
 use crate::util::camera::*;
-use crate::util::constructors::*;
-use crate::util::image::Video;
-use crate::util::shapes::{Cube, Shape, ShapeManager};
-use crate::util::vertex;
-use crate::util::vertex::VERTICES;
+use crate::util::camera_path::CameraPath;
+use crate::util::cli::{RenderOptions, WindowOptions};
+use crate::util::clock::Clock;
+use crate::util::cpu_march;
+use crate::util::gizmo::{Gizmo, GizmoSpace, Viewport};
+use crate::util::history::{Command, History, ListChange};
+use crate::util::input::{Action, Binding, InputMap, Rebinding};
+use crate::util::inspector::{Inspector, InspectorRequest, InspectorTarget};
+use crate::util::offscreen::OffscreenTarget;
+use crate::util::overlay::Overlay;
+use crate::util::recorder::{RecordFormat, Recorder};
+use crate::util::renderer::SceneRenderer;
+use crate::util::scene::SceneFile;
+use crate::util::screenshot;
+use crate::util::shapes::{ShapeManager, Union};
+use crate::util::terminal::{self, TerminalStyle};
+use crate::util::video_wall::{PlaybackMode, VideoWall, WallMapping};
+
+// Device features used when the adapter has them. Nothing needs any yet,
+// everything the shader does works on the baseline feature set.
+const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::empty();
+
+// Where captured camera keyframes are saved when no scene file is loaded.
+// A scene's keyframes go next to it, see camera_path_file.
+const CAMERA_PATH_FILE: &str = "./camera_path.ron";
+
+// Key and mouse bindings, the defaults are used for anything not in the file
+const BINDINGS_FILE: &str = "./bindings.ron";
+
+// Where recordings are written, named by the time they started
+const RECORDINGS_DIR: &str = "./recordings";
+const RECORD_FPS: u32 = 30;
+
+// Screenshots are rendered at a multiple of the window size, set by
+// --screenshot-scale, and downsampled
+const SCREENSHOTS_DIR: &str = "./screenshots";
+
+// Seconds skipped by the video seek actions
+const VIDEO_SEEK_STEP: f32 = 5.0;
+
+// Seconds the whole scene is scrubbed by the clock seek actions
+const CLOCK_SEEK_STEP: f32 = 1.0;
+
+// Video wall mappings cycled through by Action::CycleVideoMapping
+fn video_mapping_presets() -> Vec<Vec<WallMapping>> {
+    vec![
+        vec![WallMapping::Scale],
+        vec![WallMapping::Height { height: 6.0 }],
+        vec![WallMapping::Color, WallMapping::Visibility { threshold: 0.5 }],
+        vec![WallMapping::Rotation {
+            axis: (1.0, 1.0, 0.0).into(),
+            max_angle: Deg(90.0).into(),
+        }],
+    ]
+}
+
+// How far in front of the camera the inspector adds shapes
+const NEW_SHAPE_DISTANCE: f32 = 5.0;
+
+// How far in pixels the cursor can move between press and release for the
+// select button to count as a click rather than a look drag
+const CLICK_DISTANCE: f64 = 4.0;
+
+// Frame time the terminal renderer aims for
+const TERMINAL_FRAME_TIME: std::time::Duration = std::time::Duration::from_millis(33);
+
+fn initial_camera() -> Camera {
+    Camera::new((-10.0, 0.0, 0.0), Deg::<f32>(0.0), Deg::<f32>(0.0))
+}
+
+fn initial_projection(width: u32, height: u32) -> Projection {
+    Projection::new(width, height, Deg(45.0), 0.1, 100.0)
+}
+
+// Adds the demo scene to `shape_manager`
+fn build_scene(shape_manager: &mut ShapeManager) -> VideoWall {
+    let mut video_wall = VideoWall::from_dir(
+        "./assets/apple",
+        (20, 15),
+        30.0,
+        (0.0, 0.0, 0.0).into(),
+        2.0,
+        (1.0, 1.0, 1.0).into(),
+        shape_manager,
+    );
+    if video_wall.frame_count() == 0 {
+        eprintln!("No video frames found in ./assets/apple");
+    }
+    let (width, height) = video_wall.size();
+    for x in 0..width {
+        for y in 0..height {
+            let color = (0.2 + (x as f32 * 0.04), 0.2 + (y as f32 * 0.04), 0.2);
+            video_wall.set_color(x, y, color.into(), shape_manager);
+        }
+    }
+    video_wall
+}
+
+// Reads the scene file at `path`, if any. Scenes that SceneFile::validate
+// finds problems in are rejected, as the validate command would.
+fn load_scene_file(path: Option<&std::path::Path>) -> std::io::Result<Option<SceneFile>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let scene = SceneFile::load(path).map_err(|e| {
+        std::io::Error::new(e.kind(), format!("could not load {}: {}", path.display(), e))
+    })?;
+    let problems = scene.validate();
+    if !problems.is_empty() {
+        let message = format!("{} is not a valid scene: {}", path.display(), problems.join(", "));
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, message));
+    }
+    Ok(Some(scene))
+}
+
+// Adds the scene's shapes, or the demo scene's without a scene file.
+fn build_shapes(scene: Option<&SceneFile>, shape_manager: &mut ShapeManager) -> std::io::Result<VideoWall> {
+    match scene {
+        Some(scene) => scene.build(shape_manager),
+        None => Ok(build_scene(shape_manager)),
+    }
+}
+
+// The scene's camera, or the default one
+fn scene_camera(scene: Option<&SceneFile>, width: u32, height: u32) -> (Camera, Projection) {
+    let mut camera = initial_camera();
+    let mut projection = initial_projection(width, height);
+    if let Some(desc) = scene.and_then(|scene| scene.camera) {
+        desc.apply(&mut camera, &mut projection);
+    }
+    (camera, projection)
+}
 
 struct State {
     // Device & Window config
@@ -34,38 +180,60 @@ struct State {
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
-    render_pipeline: wgpu::RenderPipeline,
-
-    // Vertex Config
-    vertex_buffer: wgpu::Buffer,
-    num_vertices: u32,
+    renderer: SceneRenderer,
 
     // Shape Config
     shape_manager: ShapeManager,
-    shape_bind_group: wgpu::BindGroup,
-    shape_buffer: wgpu::Buffer,
-    sphere_buffer: wgpu::Buffer,
-    cube_buffer: wgpu::Buffer,
 
     // Camera Config
     camera: Camera,
     projection: Projection,
     camera_controller: CameraController,
-    camera_uniform: CameraUniform,
-    camera_buffer: wgpu::Buffer,
-    camera_bind_group: wgpu::BindGroup,
+
+    // Camera path
+    camera_path: CameraPath,
+    camera_path_file: PathBuf,
+    path_playing: bool,
+    path_timer: f32,
+
+    // Input
+    input_map: InputMap,
+    rebinding: Rebinding,
+
+    // Scene time, drives the shader and every animation
+    clock: Clock,
 
     // Misc config
     mouse_pressed: bool,
+    pan_pressed: bool,
+    quit_requested: bool,
     shader_params: ShaderParams,
-    config_buffer: wgpu::Buffer,
-    config_bind_group: wgpu::BindGroup,
-
-    // Apple
-    bad_apple: bool,
-    bad_apple_timer: f32,
-    bad_apple_size: (u32, u32),
-    bad_apple_video: Video,
+
+    // Video wall
+    video_wall: VideoWall,
+    video_mapping: usize, // index into video_mapping_presets
+
+    // Recording
+    recorder: Option<Recorder>,
+    record_target: Option<OffscreenTarget>,
+    record_format: RecordFormat,
+    screenshot_pending: bool,
+    screenshot_scale: u32,
+
+    // Inspector
+    overlay: Overlay,
+    inspector: Inspector,
+
+    // Selection
+    selected: Option<u32>, // index into the ShapeManager
+    cursor_pos: Option<PhysicalPosition<f64>>,
+    click_start: Option<PhysicalPosition<f64>>,
+    look_start: Option<PhysicalPosition<f64>>, // Look held, not yet dragged past a click
+    gizmo: Gizmo,
+    modifiers: ModifiersState,
+
+    // Undo and redo of scene edits
+    history: History,
 }
 
 #[repr(C)]
@@ -79,9 +247,153 @@ pub struct ShaderParams {
     shape_count: u32,
     sphere_count: u32,
     cube_count: u32,
+
+    // March quality
+    max_steps: u32,
+    epsilon: f32,
+    min_dist: f32,
+    max_dist: f32,
+    relaxation: f32,      // over-relaxation factor, 1.0 is plain sphere tracing
+    footprint_scale: f32, // scales epsilon with the pixel footprint, 0.0 disables
+
+    hud: u32, // draw overlays such as the crosshair, 0 hides them
+
+    _padding: [u32; 3], // light_pos is a vec3, aligned to 16 bytes on the GPU
+    light_pos: [f32; 3],
+    light_intensity: f32,
+
+    selected: u32, // shape outlined as selected, u32::MAX for none
+    _end_padding: [u32; 3], // uniform structs are a multiple of 16 bytes
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Quality {
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+impl Quality {
+    // (max_steps, epsilon, relaxation, footprint_scale)
+    fn march_settings(&self) -> (u32, f32, f32, f32) {
+        match self {
+            Quality::Low => (32, 0.001, 1.6, 2.0),
+            Quality::Medium => (64, 0.0005, 1.4, 1.0),
+            Quality::High => (128, 0.0001, 1.2, 0.5),
+            Quality::Ultra => (256, 0.00005, 1.0, 0.0),
+        }
+    }
 }
 
 impl ShaderParams {
+    pub fn new(width: u32, height: u32, projection: &Projection) -> Self {
+        let mut params = Self {
+            time: 0.0,
+            width,
+            height,
+            shape_count: 0,
+            sphere_count: 0,
+            cube_count: 0,
+            max_steps: 0,
+            epsilon: 0.0,
+            min_dist: projection.znear(),
+            max_dist: projection.zfar(),
+            relaxation: 1.0,
+            footprint_scale: 0.0,
+            hud: 1,
+            _padding: [0; 3],
+            light_pos: [20.0, 20.0, 15.0],
+            light_intensity: 0.6,
+            selected: u32::MAX,
+            _end_padding: [0; 3],
+        };
+        params.set_quality(Quality::Medium);
+        params
+    }
+
+    pub fn set_quality(&mut self, quality: Quality) {
+        let (max_steps, epsilon, relaxation, footprint_scale) = quality.march_settings();
+        self.max_steps = max_steps;
+        self.epsilon = epsilon;
+        self.relaxation = relaxation;
+        self.footprint_scale = footprint_scale;
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps.max(1);
+    }
+
+    pub fn set_epsilon(&mut self, epsilon: f32) {
+        self.epsilon = epsilon.max(f32::MIN_POSITIVE);
+    }
+
+    pub fn set_march_range(&mut self, min_dist: f32, max_dist: f32) {
+        self.min_dist = min_dist.max(0.0);
+        self.max_dist = max_dist.max(self.min_dist);
+    }
+
+    // Values outside [1, 2) make over-relaxation diverge, so they are clamped.
+    pub fn set_relaxation(&mut self, relaxation: f32) {
+        self.relaxation = relaxation.clamp(1.0, 1.99);
+    }
+
+    pub fn set_footprint_scale(&mut self, footprint_scale: f32) {
+        self.footprint_scale = footprint_scale.max(0.0);
+    }
+
+    pub fn set_hud(&mut self, visible: bool) {
+        self.hud = visible as u32;
+    }
+
+    pub fn hud(&self) -> bool {
+        self.hud != 0
+    }
+
+    // A single white point light, `intensity` scales its diffuse and
+    // specular contributions.
+    pub fn set_light(&mut self, pos: cgmath::Vector3<f32>, intensity: f32) {
+        self.light_pos = pos.into();
+        self.light_intensity = intensity.max(0.0);
+    }
+
+    pub fn light(&self) -> (cgmath::Vector3<f32>, f32) {
+        (self.light_pos.into(), self.light_intensity)
+    }
+
+    // Index in the ShapeManager of the shape to outline
+    pub fn set_selected(&mut self, shape: Option<u32>) {
+        self.selected = shape.unwrap_or(u32::MAX);
+    }
+
+    pub fn selected(&self) -> Option<u32> {
+        (self.selected != u32::MAX).then_some(self.selected)
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    pub fn epsilon(&self) -> f32 {
+        self.epsilon
+    }
+
+    pub fn march_range(&self) -> (f32, f32) {
+        (self.min_dist, self.max_dist)
+    }
+
+    pub fn relaxation(&self) -> f32 {
+        self.relaxation
+    }
+
+    pub fn footprint_scale(&self) -> f32 {
+        self.footprint_scale
+    }
+
     pub fn sin_t(&self) -> f32 {
         self.time.sin()
     }
@@ -102,302 +414,127 @@ impl ShaderParams {
 }
 
 impl State {
-    async fn new(window: &Window) -> State {
+    async fn new(
+        window: &Window,
+        event_loop: &EventLoopWindowTarget<()>,
+        options: &WindowOptions,
+        scene: Option<&SceneFile>,
+    ) -> Result<State, Box<dyn std::error::Error>> {
         //#region Device & Window Config
         let size = window.inner_size();
 
-        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let instance = wgpu::Instance::new(options.adapter.backends);
         let surface = unsafe { instance.create_surface(window) };
-        let adapter = request_adapter(&instance, &surface).await;
-
-        let (device, queue) = request_device(&adapter).await;
-
+        let adapter = adapter::request_adapter(&instance, Some(&surface), &options.adapter).await?;
+        log::info!("Using {}", adapter::describe_adapter(&adapter.get_info()));
+
+        let (device, queue) = adapter::request_device(&adapter, OPTIONAL_FEATURES).await?;
+
+        let format = *surface
+            .get_supported_formats(&adapter)
+            .first()
+            .ok_or_else(|| GpuError::IncompatibleSurface(adapter.get_info()))?;
+        let present_mode = match options.present_mode {
+            // always available, wgpu picks the actual mode
+            mode @ (wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync) => mode,
+            mode if surface.get_supported_present_modes(&adapter).contains(&mode) => mode,
+            mode => {
+                eprintln!("Present mode {:?} is not supported, using Fifo", mode);
+                wgpu::PresentMode::Fifo
+            }
+        };
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_supported_formats(&adapter)[0],
+            format,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode,
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
         };
         surface.configure(&device, &config);
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
+        let renderer = SceneRenderer::new(&device, config.format);
         //#endregion
 
         //#region Camera Config
-        let camera = Camera::new((-10.0, 0.0, 0.0), Deg::<f32>(0.0), Deg::<f32>(0.0));
-        let projection = Projection::new(
-            config.width,
-            config.height,
-            Deg(45.0),
-            0.1,
-            100.0);
+        let (camera, projection) = scene_camera(scene, config.width, config.height);
         let camera_controller = CameraController::new(4.0, 0.25);
-
-        let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update_view_proj(&camera, &projection);
-
-        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Camera Buffer"),
-            contents: bytemuck::cast_slice(&[camera_uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let camera_bind_group_layout = create_bind_group_layout(
-            &device,
-            0,
-            BufferBindingType::Uniform,
-            false,
-            None,
-            "camera_bind_group_layout",
-        );
-
-        let camera_bind_group = create_bind_group(
-            &device,
-            &camera_bind_group_layout,
-            0,
-            camera_buffer.as_entire_binding(),
-            "camera_bind_group",
-        );
         //#endregion
 
-        //#region config buffer
-        let shader_params = ShaderParams {
-            time: 0.0,
-            width: size.width,
-            height: size.height,
-            shape_count: 0,
-            sphere_count: 0,
-            cube_count: 0,
-        };
-
-        let config_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Camera Buffer"),
-            contents: bytemuck::cast_slice(&[shader_params]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let config_bind_group_layout = create_bind_group_layout(
-            &device,
-            1,
-            BufferBindingType::Uniform,
-            false,
-            None,
-            "config_bind_group_layout",
-        );
-
-        let config_bind_group = create_bind_group(
-            &device,
-            &config_bind_group_layout,
-            1,
-            config_buffer.as_entire_binding(),
-            "config_bind_group",
-        );
+        //#region Shader config
+        let mut shader_params = ShaderParams::new(size.width, size.height, &projection);
+        shader_params.set_quality(options.quality);
         //#endregion
 
-        //#region shape buffers
+        //#region Shapes
         let mut shape_manager = ShapeManager::new();
-        let apple_size = (20, 15);
-        for x in 0..apple_size.0 {
-            for y in 0..apple_size.1 {
-                // shape_manager.new_sphere(
-                //     ((x * 3) as f32, (y * 3) as f32, 0.0).into(),
-                //     1.0,
-                //     (0.2 + (x as f32 * 0.04), 0.2 + (y as f32 * 0.04), 0.2).into(),
-                // );
-                shape_manager.new_cube(
-                    ((x * 2) as f32, (y * 2) as f32, 0.0).into(),
-                    (1.0, 1.0, 1.0).into(),
-                    (0.2 + (x as f32 * 0.04), 0.2 + (y as f32 * 0.04), 0.2).into(),
-                );
-            }
-        }
-
-        // shape_manager.new_cube(
-        //     (0.0, 0.0, 0.0).into(),
-        //     (1.0, 1.0, 1.0).into(),
-        //     (1.0, 1.0, 1.0).into(),
-        // );
-
-        let shape_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Shape Buffer"),
-            contents: &*shape_manager.serialize_shapes(
-                camera.calc_inv_matrix(),
-                    projection.calc_matrix(),
-                (size.width as usize, size.height as usize),
-            ),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let sphere_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Sphere Buffer"),
-            contents: &*shape_manager.serialize_spheres(),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let cube_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Cube Buffer"),
-            contents: &*shape_manager.serialize_cubes(),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let shape_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: true,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: true,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: true,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                ],
-                label: Some("shape_bind_group_layout"),
-            });
-
-        let shape_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &shape_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: shape_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: sphere_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: cube_buffer.as_entire_binding(),
-                },
-            ],
-            label: Some("shape_bind_group"),
-        });
-        //#endregion
-
-        //#region render pipeline
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[
-                    &camera_bind_group_layout, // group 0
-                    &config_bind_group_layout, // group 1
-                    &shape_bind_group_layout,  // group 2
-                ],
-                push_constant_ranges: &[],
-            });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[vertex::Vertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false, // based
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
+        let video_wall = build_shapes(scene, &mut shape_manager)?;
         //#endregion
 
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        let overlay = Overlay::new(event_loop, &device, config.format, window.scale_factor() as f32);
+        let camera_path_file = camera_path_file(options.scene.as_deref());
 
-        let num_vertices = VERTICES.len() as u32;
-
-        Self {
+        Ok(Self {
             // GPU & Window config
             surface,
             device,
             queue,
             config,
             size,
-            render_pipeline,
-
-            // Vertex config
-            vertex_buffer,
-            num_vertices,
+            renderer,
 
             // Shape Config
             shape_manager,
-            shape_bind_group,
-            shape_buffer,
-            sphere_buffer,
-            cube_buffer,
 
             // Camera config
             camera,
             projection,
             camera_controller,
-            camera_uniform,
-            camera_buffer,
-            camera_bind_group,
+
+            // Camera path
+            camera_path: CameraPath::load(&camera_path_file).unwrap_or_default(),
+            camera_path_file,
+            path_playing: false,
+            path_timer: 0.0,
+
+            // Input
+            input_map: InputMap::load(BINDINGS_FILE).unwrap_or_default(),
+            rebinding: Rebinding::Idle,
+
+            clock: Clock::new(),
 
             // Misc
             mouse_pressed: false,
+            pan_pressed: false,
+            quit_requested: false,
             shader_params,
-            config_buffer,
-            config_bind_group,
 
-            // Apple
-            bad_apple: false,
-            bad_apple_timer: 0.0,
-            bad_apple_size: apple_size,
-            bad_apple_video: Video::new("./assets/apple", apple_size.0, apple_size.1),
-        }
+            // Video wall
+            video_wall,
+            video_mapping: 0,
+
+            // Recording
+            recorder: None,
+            record_target: None,
+            record_format: options.record_format,
+            screenshot_pending: false,
+            screenshot_scale: options.screenshot_scale,
+
+            // Inspector
+            overlay,
+            inspector: Inspector::default(),
+
+            // Selection
+            selected: None,
+            cursor_pos: None,
+            click_start: None,
+            look_start: None,
+            gizmo: Gizmo::default(),
+            modifiers: ModifiersState::empty(),
+
+            // Undo and redo of scene edits
+            history: History::default(),
+        })
     }
 
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -409,11 +546,37 @@ impl State {
             self.shader_params.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
             self.projection.resize(new_size.width, new_size.height);
+            if self.recorder.is_some() {
+                // recordings have a fixed size
+                println!("Window resized, stopping the recording");
+                self.stop_recording();
+            }
         }
     }
 
     fn input(&mut self, event: &WindowEvent) -> bool {
         match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_pos = Some(*position);
+                if let Some(start) = self.look_start {
+                    if (position.x - start.x).hypot(position.y - start.y) > CLICK_DISTANCE {
+                        self.look_start = None;
+                        self.mouse_pressed = true;
+                    }
+                }
+                if self.gizmo.is_dragging() {
+                    self.drag_gizmo();
+                    return true;
+                }
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor_pos = None,
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+            _ => {}
+        }
+        if self.inspector.is_visible() && self.overlay.on_event(event) {
+            return true;
+        }
+        let (binding, state) = match event {
             WindowEvent::KeyboardInput {
                 input:
                     KeyboardInput {
@@ -422,42 +585,329 @@ impl State {
                         ..
                     },
                 ..
-            } => {
-                if self.camera_controller.process_keyboard(*key, *state) {
-                    true
-                } else {
-                    match (key, state) {
-                        (VirtualKeyCode::Q, ElementState::Pressed) => {
-                            // Start / Stop bad apple
-                            self.bad_apple = !self.bad_apple;
-                            self.bad_apple_timer = 0.0;
-                        }
-                        _ => {}
-                    }
-                    false
+            } => match state {
+                // keys pressed with Ctrl held only trigger Ctrl bindings
+                ElementState::Pressed if self.modifiers.ctrl() => {
+                    let shift = self.modifiers.shift();
+                    (Binding::Ctrl { key: *key, shift }, *state)
                 }
-            }
+                _ => (Binding::Key(*key), *state),
+            },
+            WindowEvent::MouseInput { button, state, .. } => (Binding::Mouse(*button), *state),
             WindowEvent::MouseWheel { delta, .. } => {
                 self.camera_controller.process_scroll(delta);
-                true
+                return true;
             }
-            WindowEvent::MouseInput {
-                button: MouseButton::Left,
-                state,
-                ..
-            } => {
-                self.mouse_pressed = *state == ElementState::Pressed;
-                true
+            _ => return false,
+        };
+
+        let rebound = match state {
+            ElementState::Pressed => self.rebinding.process(binding, &mut self.input_map),
+            ElementState::Released => self.rebinding.release(binding, &mut self.input_map),
+        };
+        if rebound {
+            if self.rebinding == Rebinding::Idle {
+                self.save_input_map();
             }
-            _ => false,
+            return true;
+        }
+
+        let actions: Vec<Action> = self.input_map.actions(binding).collect();
+        // grabbing a gizmo handle takes the place of selecting and looking
+        if actions.contains(&Action::Select) && self.grab_gizmo(state) {
+            return true;
+        }
+        let mut handled = false;
+        for action in actions {
+            handled |= self.process_action(action, state);
         }
+        handled
     }
 
-    fn update(&mut self, dt: std::time::Duration) {
-        self.camera_controller.update_camera(&mut self.camera, dt);
-        self.camera_uniform
-            .update_view_proj(&self.camera, &self.projection);
-        self.shader_params.time += dt.as_secs_f32();
+    fn process_action(&mut self, action: Action, state: ElementState) -> bool {
+        if self.camera_controller.process_action(action, state) {
+            return true;
+        }
+        let pressed = state == ElementState::Pressed;
+        match action {
+            // a click shouldn't turn the camera, so looking waits for a drag
+            Action::Look if pressed => match self.cursor_pos {
+                Some(position) => self.look_start = Some(position),
+                None => self.mouse_pressed = true,
+            },
+            Action::Look => {
+                self.look_start = None;
+                self.mouse_pressed = false;
+            }
+            Action::Pan => self.pan_pressed = pressed,
+            Action::Select if pressed => self.click_start = self.cursor_pos,
+            Action::Select => {
+                let (Some(start), Some(end)) = (self.click_start.take(), self.cursor_pos) else {
+                    return false;
+                };
+                if (end.x - start.x).hypot(end.y - start.y) > CLICK_DISTANCE {
+                    return false;
+                }
+                self.pick((end.x as f32, end.y as f32));
+            }
+            // Everything below fires once per press
+            _ if !pressed => return false,
+            Action::PlayVideo => self.video_wall.toggle(),
+            Action::StopVideo => self.video_wall.stop(&mut self.shape_manager),
+            Action::SeekVideoBackward => {
+                self.video_wall.seek(self.video_wall.time() - VIDEO_SEEK_STEP);
+                self.video_wall.apply_frame(&mut self.shape_manager);
+            }
+            Action::SeekVideoForward => {
+                self.video_wall.seek(self.video_wall.time() + VIDEO_SEEK_STEP);
+                self.video_wall.apply_frame(&mut self.shape_manager);
+            }
+            Action::TogglePause => {
+                self.clock.toggle_pause();
+                println!("Clock {}", if self.clock.is_paused() { "paused" } else { "running" });
+            }
+            Action::StepFrame => self.clock.step(),
+            Action::SlowDown => self.set_time_scale(self.clock.time_scale() / 2.0),
+            Action::SpeedUp => self.set_time_scale(self.clock.time_scale() * 2.0),
+            Action::SeekBackward => self.seek_clock(self.clock.time() - CLOCK_SEEK_STEP),
+            Action::SeekForward => self.seek_clock(self.clock.time() + CLOCK_SEEK_STEP),
+            Action::ToggleRecording => {
+                if self.recorder.is_some() {
+                    self.stop_recording();
+                } else {
+                    self.start_recording();
+                }
+            }
+            Action::Screenshot => self.screenshot_pending = true,
+            Action::CycleRecordFormat => {
+                self.record_format = self.record_format.next();
+                println!("Record format: {:?}", self.record_format);
+            }
+            Action::CycleVideoPlayback => {
+                let mode = self.video_wall.playback_mode().next();
+                self.video_wall.set_playback_mode(mode);
+                println!("Video playback: {:?}", mode);
+            }
+            Action::CycleVideoMapping => {
+                let presets = video_mapping_presets();
+                self.video_mapping = (self.video_mapping + 1) % presets.len();
+                self.video_wall
+                    .set_mappings(presets[self.video_mapping].clone());
+                self.video_wall.apply_frame(&mut self.shape_manager);
+            }
+            Action::CaptureKeyframe => {
+                // Capture a camera keyframe
+                self.camera_path.capture(&self.camera, &self.projection);
+                self.save_camera_path();
+            }
+            Action::ClearPath => {
+                // Clear the camera path
+                self.camera_path.clear();
+                self.path_playing = false;
+                self.save_camera_path();
+            }
+            Action::PlayPath => {
+                // Play / Stop the camera path
+                self.path_playing = !self.path_playing && !self.camera_path.is_empty();
+                self.path_timer = self.camera_path.start_time();
+                self.camera_controller.sync(&self.camera);
+            }
+            Action::FrameScene => {
+                // Frame the selected shape, or the whole scene if nothing is
+                // selected
+                let selected = self
+                    .selected
+                    .and_then(|index| self.shape_manager.get_shape(index))
+                    .filter(|shape| !shape.as_any().is::<Union>())
+                    .map(|shape| shape.get_world_bounding_box());
+                if let Some(bounds) = selected.or_else(|| self.shape_manager.world_bounding_box()) {
+                    self.camera_controller
+                        .frame(&mut self.camera, &mut self.projection, bounds);
+                }
+            }
+            Action::CycleGizmoMode => {
+                self.gizmo.mode = self.gizmo.mode.next();
+                println!("Gizmo: {:?}", self.gizmo.mode);
+            }
+            Action::ToggleGizmoSpace => {
+                self.gizmo.space = match self.gizmo.space {
+                    GizmoSpace::World => GizmoSpace::Local,
+                    GizmoSpace::Local => GizmoSpace::World,
+                };
+                println!("Gizmo axes: {:?}", self.gizmo.space);
+            }
+            Action::ToggleSnapping => {
+                self.gizmo.snapping.enabled = !self.gizmo.snapping.enabled;
+                println!("Snapping {}", if self.gizmo.snapping.enabled { "on" } else { "off" });
+            }
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::ToggleInspector => self.inspector.toggle(),
+            Action::Rebind => {
+                println!("Press a key or button of the action to rebind");
+                self.rebinding = Rebinding::SelectAction;
+            }
+            // the event loop exits once this event is handled
+            Action::Quit => self.quit_requested = true,
+            _ => return false,
+        }
+        true
+    }
+
+    // Selects the shape under `coord`, in pixels from the top left corner,
+    // or clears the selection if there is none.
+    fn pick(&mut self, coord: (f32, f32)) {
+        let hit = cpu_march::pick(
+            &self.shape_manager,
+            &self.camera,
+            &self.projection,
+            &self.shader_params,
+            (self.config.width, self.config.height),
+            coord,
+        );
+        self.selected = hit.as_ref().map(|hit| hit.shape);
+        match hit {
+            Some(hit) => println!(
+                "Selected shape {} at ({:.2}, {:.2}, {:.2})",
+                hit.shape, hit.point.x, hit.point.y, hit.point.z
+            ),
+            None => println!("Selection cleared"),
+        }
+    }
+
+    // Starts dragging the gizmo handle under the cursor on press and stops on
+    // release. False if the press missed the handles.
+    fn grab_gizmo(&mut self, state: ElementState) -> bool {
+        if state == ElementState::Released {
+            let dragging = self.gizmo.is_dragging();
+            self.gizmo.end_drag();
+            self.history.end_group();
+            return dragging;
+        }
+        let (Some(cursor), Some(shape)) = (
+            self.cursor_pos,
+            self.selected.and_then(|i| self.shape_manager.get_shape(i)),
+        ) else {
+            return false;
+        };
+        let view = Viewport {
+            camera: &self.camera,
+            projection: &self.projection,
+            size: (self.config.width, self.config.height),
+        };
+        let grabbed = self
+            .gizmo
+            .begin_drag(&view, shape, (cursor.x as f32, cursor.y as f32));
+        if grabbed {
+            // the whole drag is undone at once
+            self.history.begin_group();
+        }
+        grabbed
+    }
+
+    fn drag_gizmo(&mut self) {
+        let (Some(cursor), Some(selected)) = (self.cursor_pos, self.selected) else {
+            self.gizmo.end_drag();
+            return;
+        };
+        let view = Viewport {
+            camera: &self.camera,
+            projection: &self.projection,
+            size: (self.config.width, self.config.height),
+        };
+        let coord = (cursor.x as f32, cursor.y as f32);
+        let gizmo = &mut self.gizmo;
+        self.history
+            .edit(&mut self.shape_manager, selected, |shape| gizmo.drag(&view, shape, coord));
+    }
+
+    // Edits the shape list through the history, selecting added shapes
+    fn execute(&mut self, command: Command) {
+        if let Some(change) = self.history.execute(&mut self.shape_manager, command) {
+            self.shapes_changed(change);
+            if let ListChange::Inserted { index, .. } = change {
+                self.selected = Some(index);
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        self.gizmo.end_drag();
+        match self.history.undo(&mut self.shape_manager) {
+            Some(changes) => changes.into_iter().for_each(|change| self.shapes_changed(change)),
+            None => println!("Nothing to undo"),
+        }
+    }
+
+    fn redo(&mut self) {
+        self.gizmo.end_drag();
+        match self.history.redo(&mut self.shape_manager) {
+            Some(changes) => changes.into_iter().for_each(|change| self.shapes_changed(change)),
+            None => println!("Nothing to redo"),
+        }
+    }
+
+    // Keeps indices into the ShapeManager pointing at the same shapes after
+    // `change`
+    fn shapes_changed(&mut self, change: ListChange) {
+        match change {
+            ListChange::Inserted { cube: Some(cube), .. } => self.video_wall.cube_inserted(cube),
+            ListChange::Removed { cube: Some(cube), .. } => self.video_wall.cube_removed(cube),
+            _ => {}
+        }
+        self.selected = self.selected.and_then(|index| change.shift(index));
+    }
+
+    fn save_input_map(&self) {
+        if let Err(e) = self.input_map.save(BINDINGS_FILE) {
+            eprintln!("Could not save key bindings: {}", e);
+        }
+    }
+
+    fn save_camera_path(&self) {
+        if let Err(e) = self.camera_path.save(&self.camera_path_file) {
+            eprintln!("Could not save camera path: {}", e);
+        }
+    }
+
+    fn set_time_scale(&mut self, scale: f32) {
+        self.clock.set_time_scale(scale);
+        println!("Time scale: {}x", self.clock.time_scale());
+    }
+
+    // Moves the scene clock and everything it animates to `time`.
+    fn seek_clock(&mut self, time: f32) {
+        let delta = self.clock.seek(time);
+        if self.path_playing {
+            self.path_timer += delta;
+        }
+        self.video_wall.seek(self.video_wall.time() + delta);
+        self.video_wall.apply_frame(&mut self.shape_manager);
+    }
+
+    // `elapsed` is wall clock time; camera controls follow it so the camera
+    // can still be flown while the scene is paused.
+    fn update(&mut self, window: &Window, elapsed: std::time::Duration) {
+        let dt = self.clock.tick(elapsed);
+        if self.path_playing {
+            self.path_timer += dt.as_secs_f32();
+            self.path_playing =
+                self.camera_path
+                    .apply(self.path_timer, &mut self.camera, &mut self.projection);
+            if !self.path_playing {
+                self.camera_controller.sync(&self.camera);
+            }
+        } else {
+            self.camera_controller.update_camera(&mut self.camera, elapsed);
+            self.camera_controller
+                .update_projection(&mut self.projection, elapsed);
+        }
+        // after the camera moves so the gizmo lines up with this frame
+        if self.overlay_visible() {
+            self.run_overlay(window);
+        }
+        self.shader_params.time = self.clock.time();
+        self.shader_params.set_selected(self.selected);
 
         // self.shape_manager
         //     .get_cube_mut(0)
@@ -465,60 +915,169 @@ impl State {
         //     // .set_bounds((0.1, 4.094, 5.465).into());
         //     .set_bounds((1.0, 1.0, 1.0).into());
 
-        if self.bad_apple {
-            self.bad_apple_timer += dt.as_secs_f32();
-
-            let frame = self
-                .bad_apple_video
-                .frame_index_from_time(self.bad_apple_timer, 30.0);
-            for x in 0..self.bad_apple_size.0 {
-                for y in 0..self.bad_apple_size.1 {
-                    let p = self.bad_apple_video.get_pixel_value(frame, x, y);
-                    self.shape_manager
-                        .get_cube_mut(x * self.bad_apple_size.1 + y)
-                        .unwrap()
-                        .set_bounds((p, p, p).into());
-                }
-            }
-        } else {
-            self.bad_apple_timer = 0.0;
-            self.shape_manager
-                .iter_shapes_mut()
-                .filter_map(|s| s.as_any_mut().downcast_mut::<Cube>())
-                .for_each(|c| {
-                    c.set_rotation(Quaternion::from_angle_x(Deg(0.0)));
-                    c.set_bounds((1.0, 1.0, 1.0).into());
-                });
-        }
+        self.video_wall.update(dt, &mut self.shape_manager);
 
         self.shape_manager
             .update_shader_config(&mut self.shader_params);
-        self.queue.write_buffer(
-            &self.camera_buffer,
-            0,
-            bytemuck::cast_slice(&[self.camera_uniform]),
-        );
-        self.queue.write_buffer(
-            &self.config_buffer,
-            0,
-            bytemuck::cast_slice(&[self.shader_params]),
+        self.renderer.update(
+            &self.device,
+            &self.queue,
+            &self.shape_manager,
+            &self.camera,
+            &self.projection,
+            &self.shader_params,
         );
-        self.queue.write_buffer(
-            &self.shape_buffer,
-            0,
-            &*self.shape_manager.serialize_shapes(
-                self.camera.calc_matrix(),
-                self.projection.calc_matrix(),
-                (self.size.width as usize, self.size.height as usize),
-            ),
-        );
-        self.queue.write_buffer(
-            &self.sphere_buffer,
-            0,
-            &*self.shape_manager.serialize_spheres(),
+    }
+
+    fn overlay_visible(&self) -> bool {
+        self.inspector.is_visible() || self.selected.is_some()
+    }
+
+    // Runs the inspector's UI, which edits the scene directly, and carries
+    // out what it asks for. Also draws the gizmo of the selected shape.
+    fn run_overlay(&mut self, window: &Window) {
+        let size = (self.config.width, self.config.height);
+        let locked: Vec<u32> = self
+            .video_wall
+            .cubes()
+            .iter()
+            .filter_map(|cube| self.shape_manager.cube_shape_index(*cube))
+            .collect();
+        let mut requests = vec![];
+        let cursor = self.cursor_pos.map(|p| (p.x as f32, p.y as f32));
+        self.overlay.run(window, |ctx| {
+            if self.inspector.is_visible() {
+                requests = self.inspector.show(
+                    ctx,
+                    InspectorTarget {
+                        shapes: &mut self.shape_manager,
+                        camera: &mut self.camera,
+                        projection: &mut self.projection,
+                        camera_controller: &mut self.camera_controller,
+                        params: &mut self.shader_params,
+                        selected: &mut self.selected,
+                        gizmo: &mut self.gizmo,
+                        history: &mut self.history,
+                        locked: &locked,
+                    },
+                );
+            }
+            if let Some(shape) = self.selected.and_then(|i| self.shape_manager.get_shape(i)) {
+                let view = Viewport {
+                    camera: &self.camera,
+                    projection: &self.projection,
+                    size,
+                };
+                // under the inspector window
+                let painter = ctx.layer_painter(egui::LayerId::background());
+                let active = self.gizmo.active_handle(&view, shape, cursor);
+                self.gizmo
+                    .paint(&painter, &view, shape, active, ctx.pixels_per_point());
+            }
+        });
+
+        let ahead = self.camera.position + self.camera.forward() * NEW_SHAPE_DISTANCE;
+        for request in requests {
+            match request {
+                InspectorRequest::AddSphere => self.execute(Command::AddSphere {
+                    pos: ahead.to_vec(),
+                    radius: 1.0,
+                    color: (0.8, 0.8, 0.8).into(),
+                }),
+                InspectorRequest::AddCube => self.execute(Command::AddCube {
+                    pos: ahead.to_vec(),
+                    bounds: (1.0, 1.0, 1.0).into(),
+                    color: (0.8, 0.8, 0.8).into(),
+                }),
+                InspectorRequest::Remove(index) if !locked.contains(&index) => {
+                    self.execute(Command::Remove(index))
+                }
+                InspectorRequest::Remove(_) => {}
+                InspectorRequest::Undo => self.undo(),
+                InspectorRequest::Redo => self.redo(),
+                InspectorRequest::SyncCamera => self.camera_controller.sync(&self.camera),
+            }
+        }
+    }
+
+    // Writes the buffers that depend on the output size, which is taken from
+    // `params`.
+    fn write_screen_buffers(&self, params: &ShaderParams) {
+        self.renderer.write_params(
+            &self.queue,
+            &self.shape_manager,
+            &self.camera,
+            &self.projection,
+            params,
         );
-        self.queue
-            .write_buffer(&self.cube_buffer, 0, &*self.shape_manager.serialize_cubes());
+    }
+
+    // Length of the next frame: wall clock time, or the clock's fixed step
+    // (set while recording so the video plays back at the right speed
+    // however long each frame takes to render).
+    fn frame_time(&self, elapsed: std::time::Duration) -> std::time::Duration {
+        self.clock.fixed_step().unwrap_or(elapsed)
+    }
+
+    fn start_recording(&mut self) {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |t| t.as_secs());
+        let mut path = std::path::Path::new(RECORDINGS_DIR).join(format!("recording_{}", secs));
+        if let Some(extension) = self.record_format.extension() {
+            path.set_extension(extension);
+        }
+        let (width, height) = (self.config.width, self.config.height);
+        match Recorder::new(&path, self.record_format, width, height, RECORD_FPS) {
+            Ok(recorder) => {
+                println!("Recording to {}", path.display());
+                self.record_target = Some(OffscreenTarget::new(&self.device, width, height, self.config.format));
+                self.clock.set_fixed_step(Some(recorder.frame_time()));
+                self.recorder = Some(recorder);
+            }
+            Err(e) => eprintln!("Could not start recording: {}", e),
+        }
+    }
+
+    fn stop_recording(&mut self) {
+        self.record_target = None;
+        if let Some(recorder) = self.recorder.take() {
+            self.clock.set_fixed_step(None);
+            let path = recorder.path().to_path_buf();
+            match recorder.finish() {
+                Ok(frames) => println!("Recorded {} frames to {}", frames, path.display()),
+                Err(e) => eprintln!("Could not finish recording: {}", e),
+            }
+        }
+    }
+
+    // Renders the frame again into the recording's texture and writes it out.
+    fn record_frame(&mut self) {
+        let target = match &self.record_target {
+            Some(target) => target,
+            None => return,
+        };
+        // recordings leave out the HUD, as screenshots do
+        let mut params = self.shader_params;
+        params.set_hud(false);
+        self.write_screen_buffers(&params);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Record Encoder"),
+            });
+        self.renderer.draw(&mut encoder, target.view());
+        self.queue.submit(std::iter::once(encoder.finish()));
+        self.write_screen_buffers(&self.shader_params);
+
+        let result = target
+            .read_rgba(&self.device, &self.queue)
+            .and_then(|frame| self.recorder.as_mut().unwrap().write_frame(&frame));
+        if let Err(e) = result {
+            eprintln!("Could not record frame: {}", e);
+            self.stop_recording();
+        }
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -532,57 +1091,89 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-
-            render_pass.set_pipeline(&self.render_pipeline);
-
-            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-
-            render_pass.set_bind_group(1, &self.config_bind_group, &[]);
-
-            render_pass.set_bind_group(
-                2,
-                &self.shape_bind_group,
-                &[
-                    DynamicOffset::default(),
-                    DynamicOffset::default(),
-                    DynamicOffset::default(),
-                ],
-            );
-
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.draw(0..self.num_vertices, 0..1);
+        self.renderer.draw(&mut encoder, &view);
+        // the overlay is left out of recordings and screenshots
+        if self.overlay_visible() {
+            let size = (self.config.width, self.config.height);
+            self.overlay
+                .paint(&self.device, &self.queue, &mut encoder, &view, size);
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
 
+        self.record_frame();
+        if std::mem::take(&mut self.screenshot_pending) {
+            self.take_screenshot();
+        }
+
         Ok(())
     }
+
+    // Renders the current view without the HUD at screenshot_scale times the
+    // window size, as far as the GPU's texture size limit allows, downsamples
+    // it and saves it as a PNG.
+    fn take_screenshot(&mut self) {
+        let (width, height) = (self.config.width, self.config.height);
+        let max_size = self.device.limits().max_texture_dimension_2d;
+        let scale = self.screenshot_scale.min(max_size / width.max(height)).max(1);
+
+        let mut params = self.shader_params;
+        params.width = width * scale;
+        params.height = height * scale;
+        params.set_hud(false);
+        self.write_screen_buffers(&params);
+
+        let target = OffscreenTarget::new(&self.device, params.width, params.height, self.config.format);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Screenshot Encoder"),
+            });
+        self.renderer.draw(&mut encoder, target.view());
+        self.queue.submit(std::iter::once(encoder.finish()));
+        let pixels = target.read_rgba(&self.device, &self.queue);
+        // back to the window's size for the next frame
+        self.write_screen_buffers(&self.shader_params);
+
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |t| t.as_millis());
+        let path = std::path::Path::new(SCREENSHOTS_DIR).join(format!("screenshot_{}.png", millis));
+        let result = pixels.and_then(|pixels| {
+            let pixels = screenshot::downsample(&pixels, params.width, params.height, scale);
+            screenshot::save_png(&path, pixels, width, height)
+        });
+        match result {
+            Ok(()) => println!("Saved screenshot to {}", path.display()),
+            Err(e) => eprintln!("Could not save screenshot: {}", e),
+        }
+    }
 }
 
-pub async fn run() {
+// Keyframes for scene.ron are kept in scene.path.ron
+fn camera_path_file(scene: Option<&Path>) -> PathBuf {
+    match scene {
+        Some(scene) => scene.with_extension("path.ron"),
+        None => PathBuf::from(CAMERA_PATH_FILE),
+    }
+}
+
+// Opens the window and renders on the adapter picked by `options`. Only
+// returns if the scene or the GPU could not be set up.
+pub async fn run(options: WindowOptions) -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
+    let scene = load_scene_file(options.scene.as_deref())?;
 
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let mut builder = WindowBuilder::new();
+    if let Some((width, height)) = options.size {
+        builder = builder.with_inner_size(winit::dpi::PhysicalSize::new(width, height));
+    }
+    if options.fullscreen {
+        builder = builder.with_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+    }
+    let window = builder.build(&event_loop)?;
 
     // window
     //     .set_cursor_grab(CursorGrabMode::Locked)
@@ -590,7 +1181,7 @@ pub async fn run() {
     //     .or_else(|_| window.set_cursor_grab(CursorGrabMode::None))
     //     .unwrap();
 
-    let mut state = State::new(&window).await;
+    let mut state = State::new(&window, &event_loop, &options, scene.as_ref()).await?;
     let mut last_render_time = instant::Instant::now();
 
     event_loop.run(move |event, _, control_flow| {
@@ -602,20 +1193,12 @@ pub async fn run() {
             } if window_id == window.id() && !state.input(event) => {
                 match event {
                     #[cfg(not(target_arch="wasm32"))]
-                    WindowEvent::CloseRequested
-                    | WindowEvent::KeyboardInput {
-                        input:
-                        KeyboardInput {
-                            state: ElementState::Pressed,
-                            virtual_keycode: Some(VirtualKeyCode::Escape),
-                            ..
-                        },
-                        ..
-                    } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
                     WindowEvent::Resized(physical_size) => {
                         state.resize(*physical_size);
                     }
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                    WindowEvent::ScaleFactorChanged { new_inner_size, scale_factor } => {
+                        state.overlay.set_pixels_per_point(*scale_factor as f32);
                         state.resize(**new_inner_size);
                     }
                     _ => {}
@@ -625,17 +1208,21 @@ pub async fn run() {
             Event::DeviceEvent {
                 event: DeviceEvent::MouseMotion{ delta, },
                 .. // We're not using device_id currently
-            } =>
+            } => {
                 if state.mouse_pressed {
-                state.camera_controller.process_mouse(delta.0, delta.1)
+                    state.camera_controller.process_mouse(delta.0, delta.1)
+                }
+                if state.pan_pressed {
+                    state.camera_controller.process_pan(delta.0, delta.1)
+                }
             }
             // state.camera_controller.process_mouse(delta.0, delta.1),
 
             Event::RedrawRequested(window_id) if window_id == window.id() => {
                 let now = instant::Instant::now();
-                let dt = now - last_render_time;
+                let dt = state.frame_time(now - last_render_time);
                 last_render_time = now;
-                state.update(dt);
+                state.update(&window, dt);
                 match state.render() {
                     Ok(_) => {}
                     Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
@@ -647,7 +1234,129 @@ pub async fn run() {
             Event::MainEventsCleared => {
                 window.request_redraw();
             }
+
+            // finish the file so it is playable
+            Event::LoopDestroyed => state.stop_recording(),
             _ => {}
         }
+        #[cfg(not(target_arch="wasm32"))]
+        if state.quit_requested {
+            *control_flow = ControlFlow::Exit;
+        }
     });
 }
+
+// Renders the scene on the CPU into the terminal, for machines without a GPU.
+// The video wall plays on a loop until the process is interrupted.
+pub fn run_terminal(style: TerminalStyle, scene: Option<&std::path::Path>) -> std::io::Result<()> {
+    let scene = load_scene_file(scene)?;
+    let (columns, rows) = terminal::terminal_size();
+    // leave the last line free so the terminal does not scroll
+    let (width, height) = terminal::pixel_size(columns, rows.saturating_sub(1).max(1));
+
+    let mut shape_manager = ShapeManager::new();
+    let mut video_wall = build_shapes(scene.as_ref(), &mut shape_manager)?;
+    video_wall.set_playback_mode(PlaybackMode::Loop);
+    video_wall.play();
+    let (camera, projection) = scene_camera(scene.as_ref(), width, height);
+    let mut shader_params = ShaderParams::new(width, height, &projection);
+    let mut clock = Clock::new();
+
+    print!("\x1b[2J");
+    let mut last_render_time = instant::Instant::now();
+    loop {
+        let now = instant::Instant::now();
+        let dt = clock.tick(now - last_render_time);
+        last_render_time = now;
+        video_wall.update(dt, &mut shape_manager);
+        shader_params.time = clock.time();
+
+        let pixels = cpu_march::render(&shape_manager, &camera, &projection, &shader_params, width, height);
+        let frame = terminal::format_frame(&pixels, width, height, style);
+        let mut stdout = std::io::stdout().lock();
+        if write!(stdout, "\x1b[H{}", frame).and_then(|_| stdout.flush()).is_err() {
+            return Ok(()); // stdout was closed
+        }
+        drop(stdout);
+
+        if let Some(rest) = TERMINAL_FRAME_TIME.checked_sub(now.elapsed()) {
+            std::thread::sleep(rest);
+        }
+    }
+}
+
+// Renders one frame through the same pipeline as the window into an
+// offscreen texture, on the adapter picked by `options` without needing a
+// surface. Returns tightly packed RGBA8 rows in sRGB, as the window shows
+// them.
+pub async fn render_offscreen(
+    options: &AdapterOptions,
+    shapes: &ShapeManager,
+    camera: &Camera,
+    projection: &Projection,
+    params: &ShaderParams,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let instance = wgpu::Instance::new(options.backends);
+    let adapter = adapter::request_adapter(&instance, None, options).await?;
+    log::info!("Rendering on {}", adapter::describe_adapter(&adapter.get_info()));
+    let (device, queue) = adapter::request_device(&adapter, OPTIONAL_FEATURES).await?;
+
+    let max_size = device.limits().max_texture_dimension_2d;
+    if params.width.max(params.height) > max_size {
+        let message = format!("the GPU cannot render images larger than {}x{}", max_size, max_size);
+        return Err(message.into());
+    }
+
+    let mut params = *params;
+    shapes.update_shader_config(&mut params);
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let mut renderer = SceneRenderer::new(&device, format);
+    renderer.update(&device, &queue, shapes, camera, projection, &params);
+
+    let target = OffscreenTarget::new(&device, params.width, params.height, format);
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Offscreen Encoder"),
+    });
+    renderer.draw(&mut encoder, target.view());
+    queue.submit(std::iter::once(encoder.finish()));
+    Ok(target.read_rgba(&device, &queue)?)
+}
+
+// Renders a single frame through the GPU pipeline without a window and
+// saves it as a PNG. When no adapter matches `options.adapter` the frame is
+// ray marched on the CPU instead, which is much slower but looks the same.
+pub fn render_to_file(options: &RenderOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let scene = load_scene_file(options.scene.as_deref())?;
+    let (width, height) = options.size;
+    let mut shape_manager = ShapeManager::new();
+    let mut video_wall = build_shapes(scene.as_ref(), &mut shape_manager)?;
+    let (mut camera, mut projection) = scene_camera(scene.as_ref(), width, height);
+    if let Some(desc) = &options.camera {
+        desc.apply(&mut camera, &mut projection);
+    }
+    let mut shader_params = ShaderParams::new(width, height, &projection);
+    shader_params.set_quality(options.quality);
+    shader_params.set_hud(false);
+    shader_params.time = options.time;
+    video_wall.seek(options.time);
+    video_wall.wait_frame(&mut shape_manager);
+
+    let rendered = pollster::block_on(render_offscreen(
+        &options.adapter,
+        &shape_manager,
+        &camera,
+        &projection,
+        &shader_params,
+    ));
+    let rgba = match rendered {
+        Ok(rgba) => rgba,
+        Err(e) if e.is::<GpuError>() => {
+            eprintln!("{}, rendering on the CPU", e);
+            let pixels =
+                cpu_march::render(&shape_manager, &camera, &projection, &shader_params, width, height);
+            cpu_march::to_rgba8(&pixels)
+        }
+        Err(e) => return Err(e),
+    };
+    Ok(screenshot::save_png(&options.out, rgba, width, height)?)
+}
@@ -1,6 +1,56 @@
-use ray_marching::run;
-use ray_marching::util::image::Video;
+use ray_marching::util::adapter;
+use ray_marching::util::cli::{self, Command};
+use ray_marching::util::scene::SceneFile;
+use ray_marching::{render_to_file, run, run_terminal};
 
 fn main() {
-    pollster::block_on(run());
+    let command = match cli::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match command {
+        Command::Help => print!("{}", cli::USAGE),
+        Command::ListAdapters(backends) => {
+            let adapters = adapter::list_adapters(backends);
+            if adapters.is_empty() {
+                println!("No adapters found on {:?}", backends);
+            }
+            for (i, info) in adapters.iter().enumerate() {
+                println!("{}: {}", i, adapter::describe_adapter(info));
+            }
+        }
+        Command::Validate(path) => {
+            let problems = SceneFile::load(&path)
+                .map(|scene| scene.validate())
+                .unwrap_or_else(|e| exit_with(format!("could not load {}: {}", path.display(), e)));
+            for problem in &problems {
+                println!("{}: {}", path.display(), problem);
+            }
+            if !problems.is_empty() {
+                std::process::exit(1);
+            }
+            println!("{} is valid", path.display());
+        }
+        Command::Render(options) => {
+            render_to_file(&options).unwrap_or_else(|e| exit_with(e));
+            println!("Rendered {}", options.out.display());
+        }
+        Command::Terminal { style, scene } => {
+            run_terminal(style, scene.as_deref()).unwrap_or_else(|e| exit_with(e))
+        }
+        Command::Window(options) => {
+            if let Err(e) = pollster::block_on(run(options)) {
+                exit_with(e);
+            }
+        }
+    }
+}
+
+fn exit_with(error: impl std::fmt::Display) -> ! {
+    eprintln!("Error: {}", error);
+    std::process::exit(1);
 }
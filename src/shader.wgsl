@@ -1,6 +1,8 @@
 //#region constants
 let EPSILON: f32 = 0.0001;
 let ambient_light: vec3<f32> = vec3<f32>(0.5, 0.5, 0.5);
+let OUTLINE_WIDTH: f32 = 2.0; // in pixels
+let outline_color: vec3<f32> = vec3<f32>(1.0, 0.6, 0.1);
 //#endregion
 
 //#region typedefs
@@ -16,6 +18,9 @@ struct VertexOutput {
 struct CameraUniform {
     view_pos: vec4<f32>,
     view_angle: mat4x4<f32>,
+    projection: vec4<f32>, // fovy (radians), aspect, znear, zfar
+    mode: u32, // 0 = perspective, 1 = orthographic
+    ortho_extent: f32,
 };
 
 struct Config {
@@ -26,12 +31,25 @@ struct Config {
     shape_count: u32,
     sphere_count: u32,
     prism_count: u32,
+
+    max_steps: u32,
+    epsilon: f32,
+    min_dist: f32,
+    max_dist: f32,
+    relaxation: f32,
+    footprint_scale: f32,
+    hud: u32, // 0 hides the crosshair
+
+    light_pos: vec3<f32>,
+    light_intensity: f32,
+    selected: u32, // index into shapes of the outlined shape, out of range for none
 }
 
 struct Shape {
     color: vec4<f32>,
     index: u32,
     shape_type: u32,
+    flags: u32, // bit 0 = enabled
     bounding_box: vec4<f32>,
 };
 
@@ -74,14 +92,22 @@ fn rgb_to_srgb(rgb: vec4<f32>) -> vec4<f32> {
     return vec4<f32>(x, y, z, rgb.w);
 }
 
+// fov is the vertical field of view in radians
 fn ray_direction(fov: f32, size: vec2<f32>, coord: vec2<f32>) -> vec3<f32> {
     var xy: vec2<f32> = coord - size / 2.0;
-    var z: f32 = (size.y / 2.0) / tan(radians(fov) / 2.0);
+    var z: f32 = (size.y / 2.0) / tan(fov / 2.0);
     return normalize(vec3<f32>(xy, -z));
 }
 
+// offset of the ray origin on the image plane for orthographic rays,
+// extent is half the height of the view in world units
+fn ortho_ray_origin(extent: f32, size: vec2<f32>, coord: vec2<f32>) -> vec3<f32> {
+    let xy = (coord - size / 2.0) / (size.y / 2.0) * extent;
+    return vec3<f32>(xy, 0.0);
+}
+
 fn inv_ray_direction(fov: f32, size: vec2<f32>, world_coord: vec3<f32>) -> vec2<f32> {
-    var z: f32 = (size.y / 2.0) / tan(radians(fov) / 2.0);
+    var z: f32 = (size.y / 2.0) / tan(fov / 2.0);
     let scale = z / world_coord.z;
     let scaled_coord = world_coord * scale;
     return world_coord.xy + size / 2.0;
@@ -124,8 +150,15 @@ fn scene_sdf(sample_point: vec3<f32>, pixel_coord: vec2<f32>) -> vec4<f32> {
     for (var i: i32 = 0; i < i32(config.shape_count); i++) {
         var dist: f32 = 100.0;
 
-        let min = shapes[i].bounding_box.xy;
-        let max = shapes[i].bounding_box.zw;
+        if ((shapes[i].flags & u32(1)) == u32(0)) {
+            continue;
+        }
+
+        // grown by the footprint hit tolerance, which lets rays grazing a
+        // silhouette hit up to footprint_scale pixels outside of it
+        let margin = config.footprint_scale + 1.0;
+        let min = shapes[i].bounding_box.xy - margin;
+        let max = shapes[i].bounding_box.zw + margin;
         if (pixel_coord.x < min.x || pixel_coord.x > max.x || pixel_coord.y < min.y || pixel_coord.y > max.y) {
             continue;
         }
@@ -152,14 +185,71 @@ fn scene_sdf(sample_point: vec3<f32>, pixel_coord: vec2<f32>) -> vec4<f32> {
     return vec4<f32>(color, min_dist);
 }
 
-fn shortest_distance_to_surface(eye: vec3<f32>, dir: vec3<f32>, start: f32, MAX_DIST: f32, pixel_coord: vec2<f32>) -> vec4<f32> {
+// Distance to the selected shape, 100.0 if nothing enabled is selected
+fn selected_sdf(sample_point: vec3<f32>) -> f32 {
+    if (config.selected >= config.shape_count) {
+        return 100.0;
+    }
+    let shape = shapes[config.selected];
+    if ((shape.flags & u32(1)) == u32(0)) {
+        return 100.0;
+    }
+    if (shape.shape_type == u32(0)) {
+        return sphere_sdf(sample_point, spheres[shape.index]);
+    } else if (shape.shape_type == u32(1)) {
+        let prism = prisms[shape.index];
+        return cube_sdf(sample_point, prism.model, prism.size, prism.rot);
+    }
+    return 100.0;
+}
+
+// How close the ray comes to the selected shape before max_depth, in pixels
+// at the depth of the closest approach, or 0.0 if it hits the shape.
+fn selection_distance(eye: vec3<f32>, dir: vec3<f32>, start: f32, max_depth: f32, pixel_size: vec2<f32>) -> f32 {
     var depth: f32 = start;
-    for (var i: i32 = 0; i < 50; i++) {
+    var closest: f32 = 1000000.0;
+    for (var i: u32 = u32(0); i < config.max_steps; i++) {
+        let dist = selected_sdf(eye + depth * dir);
+        if (dist < config.epsilon) {
+            return 0.0;
+        }
+        closest = min(closest, dist / (pixel_size.x + depth * pixel_size.y));
+        depth += dist;
+        if (depth >= max_depth) {
+            break;
+        }
+    }
+    return closest;
+}
+
+// Over-relaxed sphere tracing (Keinert et al. 2014). Steps are scaled by
+// config.relaxation, and if two consecutive unbounding spheres stop overlapping
+// we have overshot, so we step back and continue with plain sphere tracing.
+// pixel_size.x is the pixel footprint at the ray origin and pixel_size.y is how
+// much it grows per unit of depth.
+fn shortest_distance_to_surface(eye: vec3<f32>, dir: vec3<f32>, start: f32, MAX_DIST: f32, pixel_size: vec2<f32>, pixel_coord: vec2<f32>) -> vec4<f32> {
+    var depth: f32 = start;
+    var omega: f32 = config.relaxation;
+    var prev_radius: f32 = 0.0;
+    var step_length: f32 = 0.0;
+    for (var i: u32 = u32(0); i < config.max_steps; i++) {
         let dist = scene_sdf(eye + depth * dir, pixel_coord);
-        if (dist.w < EPSILON) {
+        let radius = abs(dist.w);
+        let overshot = omega > 1.0 && (radius + prev_radius) < step_length;
+        if (overshot) {
+            step_length -= omega * step_length;
+            omega = 1.0;
+        } else {
+            step_length = dist.w * omega;
+        }
+        prev_radius = radius;
+
+        // hits only count once the distance estimate can be trusted again
+        let epsilon = max(config.epsilon, (pixel_size.x + depth * pixel_size.y) * config.footprint_scale);
+        if (!overshot && radius < epsilon) {
             return vec4<f32>(dist.xyz, depth);
         }
-        depth += dist.w;
+        depth += step_length;
         if (depth >= MAX_DIST) {
             return vec4<f32>(0.1, 0.2, 0.3, MAX_DIST);
         }
@@ -220,8 +310,8 @@ fn phong_illumination(
 ) -> vec3<f32> {
     var color: vec3<f32> = ambient_light * k_a;
 
-    let l1_pos = vec3<f32>(20.0, 20.0, 15.0);
-    let l1_intensity = vec3<f32>(0.6, 0.6, 0.6);
+    let l1_pos = config.light_pos;
+    let l1_intensity = vec3<f32>(config.light_intensity);
 
     color += phong_contrib(k_d, k_s, alpha, p, eye, l1_pos, l1_intensity, pixel_coord);
 
@@ -248,22 +338,43 @@ fn vs_main(
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let MIN_DIST = 0.0;
-    let MAX_DIST = 100.0;
+    // march no further than either the quality settings or the clip planes allow
+    let MIN_DIST = max(config.min_dist, camera.projection.z);
+    let MAX_DIST = min(config.max_dist, camera.projection.w);
+    let fov = camera.projection.x;
 
     let screen_size = vec2<f32>(f32(config.width), f32(config.height));
 //    let pixel_coord = map_screen_space(screen_size, in.position.xy);
 
     let pixel_coord = vec2<f32>(in.clip_position.x, in.clip_position.y);
-    if ((i32(pixel_coord.x) == i32(screen_size.x / 2.0) || i32(pixel_coord.y) == i32(screen_size.y / 2.0)) && (abs(pixel_coord.x - screen_size.x / 2.0) < 10.0 && abs(pixel_coord.y - screen_size.y / 2.0) < 10.0)) {
+    if (config.hud != 0u && (i32(pixel_coord.x) == i32(screen_size.x / 2.0) || i32(pixel_coord.y) == i32(screen_size.y / 2.0)) && (abs(pixel_coord.x - screen_size.x / 2.0) < 10.0 && abs(pixel_coord.y - screen_size.y / 2.0) < 10.0)) {
         return vec4<f32>(0.5, 0.5, 0.5, 1.0);
     }
 
-    let eye = camera.view_pos.xyz;
-    var dir: vec3<f32> = ray_direction(45.0, screen_size, pixel_coord);
-    dir = dir * mat3x3<f32>(camera.view_angle[0].xyz, camera.view_angle[1].xyz, camera.view_angle[2].xyz); // get 3x3 submatrix
-
-    let dist = shortest_distance_to_surface(eye, dir, 0.0, 100.0, pixel_coord);
+    let view = mat3x3<f32>(camera.view_angle[0].xyz, camera.view_angle[1].xyz, camera.view_angle[2].xyz); // get 3x3 submatrix
+    var eye: vec3<f32> = camera.view_pos.xyz;
+    var dir: vec3<f32>;
+    var pixel_size: vec2<f32>;
+    if (camera.mode == u32(1)) {
+        // orthographic: parallel rays starting across the image plane
+        eye = eye + ortho_ray_origin(camera.ortho_extent, screen_size, pixel_coord) * view;
+        dir = vec3<f32>(0.0, 0.0, -1.0) * view;
+        pixel_size = vec2<f32>(2.0 * camera.ortho_extent / screen_size.y, 0.0);
+    } else {
+        dir = ray_direction(fov, screen_size, pixel_coord) * view;
+        // angle subtended by one pixel, used to grow epsilon with distance
+        pixel_size = vec2<f32>(0.0, 2.0 * tan(fov / 2.0) / screen_size.y);
+    }
+    let dist = shortest_distance_to_surface(eye, dir, MIN_DIST, MAX_DIST, pixel_size, pixel_coord);
+
+    if (config.hud != 0u && config.selected < config.shape_count) {
+        // the outline goes around the selected shape, not over it
+        let hit_epsilon = max(config.epsilon, (pixel_size.x + dist.w * pixel_size.y) * config.footprint_scale);
+        let on_selection = dist.w < MAX_DIST - EPSILON && selected_sdf(eye + dist.w * dir) < hit_epsilon;
+        if (!on_selection && selection_distance(eye, dir, MIN_DIST, dist.w, pixel_size) < OUTLINE_WIDTH) {
+            return vec4<f32>(outline_color, 1.0);
+        }
+    }
     if (dist.w >= MAX_DIST - EPSILON) {
 
         // didn't hit anything
@@ -0,0 +1,175 @@
+use std::fmt;
+
+use wgpu::{Adapter, AdapterInfo, Backends, Device, Features, Instance, Limits, Queue, Surface};
+
+#[derive(Debug)]
+pub enum GpuError {
+    UnknownBackend(String),
+    // no adapter on `backends` (matching `name`, if given) can draw to the window
+    NoAdapter {
+        backends: Backends,
+        name: Option<String>,
+    },
+    RequestDevice(wgpu::RequestDeviceError),
+    IncompatibleSurface(AdapterInfo),
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GpuError::UnknownBackend(name) => write!(
+                f,
+                "unknown backend '{}', expected one of vulkan, metal, dx12, dx11, gl, webgpu, primary or all",
+                name
+            ),
+            GpuError::NoAdapter {
+                backends,
+                name: Some(name),
+            } => write!(
+                f,
+                "no adapter named '{}' on {:?}, --list-adapters shows what is available",
+                name, backends
+            ),
+            GpuError::NoAdapter { backends, name: None } => {
+                write!(f, "no usable graphics adapter on {:?}", backends)
+            }
+            GpuError::RequestDevice(e) => write!(f, "could not open the graphics device: {}", e),
+            GpuError::IncompatibleSurface(info) => {
+                write!(f, "{} cannot draw to the window", info.name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GpuError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GpuError::RequestDevice(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for GpuError {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        GpuError::RequestDevice(e)
+    }
+}
+
+// How to pick the adapter to render with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdapterOptions {
+    pub backends: Backends,
+    // case insensitive part of the adapter's name, see `--list-adapters`
+    pub name: Option<String>,
+    // skip hardware adapters and go straight to the software fallback
+    pub software: bool,
+}
+
+impl Default for AdapterOptions {
+    fn default() -> Self {
+        Self {
+            // WGPU_BACKEND works as it does in the wgpu examples
+            backends: wgpu::util::backend_bits_from_env().unwrap_or_else(Backends::all),
+            name: None,
+            software: false,
+        }
+    }
+}
+
+pub fn parse_backends(name: &str) -> Result<Backends, GpuError> {
+    match name.to_lowercase().as_str() {
+        "vulkan" | "vk" => Ok(Backends::VULKAN),
+        "metal" | "mtl" => Ok(Backends::METAL),
+        "dx12" | "d3d12" => Ok(Backends::DX12),
+        "dx11" | "d3d11" => Ok(Backends::DX11),
+        "gl" | "opengl" | "gles" => Ok(Backends::GL),
+        "webgpu" | "browser" => Ok(Backends::BROWSER_WEBGPU),
+        "primary" => Ok(Backends::PRIMARY),
+        "all" => Ok(Backends::all()),
+        _ => Err(GpuError::UnknownBackend(name.to_string())),
+    }
+}
+
+// Every adapter on `backends`, as shown by `--list-adapters`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn list_adapters(backends: Backends) -> Vec<AdapterInfo> {
+    Instance::new(backends)
+        .enumerate_adapters(backends)
+        .map(|adapter| adapter.get_info())
+        .collect()
+}
+
+pub fn describe_adapter(info: &AdapterInfo) -> String {
+    format!("{} ({:?}, {:?})", info.name, info.backend, info.device_type)
+}
+
+// Picks the adapter named in `options`, or else the default one, falling
+// back to a software adapter when there is no hardware adapter that can
+// draw to `surface`.
+pub async fn request_adapter(
+    instance: &Instance,
+    surface: Option<&Surface>,
+    options: &AdapterOptions,
+) -> Result<Adapter, GpuError> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(name) = &options.name {
+        let name = name.to_lowercase();
+        return instance
+            .enumerate_adapters(options.backends)
+            .filter(|adapter| surface.is_none_or(|s| adapter.is_surface_supported(s)))
+            .find(|adapter| adapter.get_info().name.to_lowercase().contains(&name))
+            .ok_or_else(|| GpuError::NoAdapter {
+                backends: options.backends,
+                name: options.name.clone(),
+            });
+    }
+
+    let request = |force_fallback_adapter| {
+        instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: surface,
+            force_fallback_adapter,
+        })
+    };
+    if !options.software {
+        if let Some(adapter) = request(false).await {
+            return Ok(adapter);
+        }
+        log::warn!("No hardware adapter found, trying a software adapter");
+    }
+    request(true).await.ok_or(GpuError::NoAdapter {
+        backends: options.backends,
+        name: None,
+    })
+}
+
+// Requests the device with whichever of `optional_features` the adapter has.
+// Limits are the wgpu defaults, lowered to the downlevel defaults on
+// adapters that can't meet them, with the adapter's own texture size limit
+// so screenshots can be as large as possible.
+pub async fn request_device(
+    adapter: &Adapter,
+    optional_features: Features,
+) -> Result<(Device, Queue), GpuError> {
+    let defaults = if cfg!(target_arch = "wasm32") {
+        Limits::downlevel_webgl2_defaults()
+    } else if Limits::default().check_limits(&adapter.limits()) {
+        Limits::default()
+    } else {
+        Limits::downlevel_defaults()
+    };
+    let limits = defaults.using_resolution(adapter.limits());
+    let features = optional_features & adapter.features();
+    let device = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                features,
+                limits,
+                label: None,
+            },
+            None,
+        )
+        .await?;
+    Ok(device)
+}
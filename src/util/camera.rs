@@ -1,7 +1,12 @@
-use cgmath::{perspective, InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Vector3, Matrix};
+use cgmath::{
+    ortho, perspective, EuclideanSpace, InnerSpace, Matrix4, Point3, Quaternion, Rad, Rotation,
+    Rotation3, SquareMatrix, Vector3,
+};
 use std::time::Duration;
 use winit::dpi::PhysicalPosition;
-use winit::event::{ElementState, MouseScrollDelta, VirtualKeyCode};
+use winit::event::{ElementState, MouseScrollDelta};
+
+use super::input::Action;
 
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
@@ -13,11 +18,27 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
 
 pub const SAFE_FRAC_PI_2: f32 = 1.0;
 
+// Field of view limits for zooming, in radians
+pub const MIN_FOVY: f32 = 0.1745; // 10 degrees
+pub const MAX_FOVY: f32 = 2.0944; // 120 degrees
+
+// Orthographic half-height limits for zooming, in world units
+pub const MIN_ORTHO_EXTENT: f32 = 0.1;
+pub const MAX_ORTHO_EXTENT: f32 = 1000.0;
+
+// Closest the orbit camera may get to its target
+pub const MIN_ORBIT_DISTANCE: f32 = 0.1;
+
+// The camera looks down +x with +y up and +z to the right when unrotated.
+// By default it is steered with yaw and pitch about the world's y axis, which
+// keeps the horizon level. In free mode the yaw and pitch are replaced by an
+// orientation quaternion, so the camera can roll and look in any direction.
 #[derive(Debug)]
 pub struct Camera {
     pub position: Point3<f32>,
     yaw: Rad<f32>,
     pitch: Rad<f32>,
+    orientation: Option<Quaternion<f32>>,
 }
 
 impl Camera {
@@ -30,29 +51,143 @@ impl Camera {
             position: position.into(),
             yaw: yaw.into(),
             pitch: pitch.into(),
+            orientation: None,
         }
     }
 
-    pub fn calc_matrix(&self) -> Matrix4<f32> {
-        let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
-        let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
+    pub fn is_free(&self) -> bool {
+        self.orientation.is_some()
+    }
+
+    // Switches between yaw/pitch and free orientation, keeping the view
+    // direction. Leaving free mode drops any roll.
+    pub fn set_free(&mut self, free: bool) {
+        if free == self.is_free() {
+            return;
+        }
+        if free {
+            self.orientation = Some(self.orientation());
+        } else {
+            let forward = self.forward();
+            self.orientation = None;
+            self.yaw = Rad(forward.z.atan2(forward.x));
+            self.pitch = Rad(forward.y.clamp(-1.0, 1.0).asin());
+            self.clamp_pitch();
+        }
+    }
+
+    // Derived from the orientation in free mode
+    pub fn yaw(&self) -> Rad<f32> {
+        match self.orientation {
+            Some(_) => {
+                let forward = self.forward();
+                Rad(forward.z.atan2(forward.x))
+            }
+            None => self.yaw,
+        }
+    }
+
+    // Derived from the orientation in free mode
+    pub fn pitch(&self) -> Rad<f32> {
+        match self.orientation {
+            Some(_) => Rad(self.forward().y.clamp(-1.0, 1.0).asin()),
+            None => self.pitch,
+        }
+    }
+
+    pub fn set_yaw_pitch<Y: Into<Rad<f32>>, P: Into<Rad<f32>>>(&mut self, yaw: Y, pitch: P) {
+        self.yaw = yaw.into();
+        self.pitch = pitch.into();
+        if self.orientation.is_some() {
+            self.orientation = Some(Self::yaw_pitch_orientation(self.yaw, self.pitch));
+        }
+    }
+
+    pub fn orientation(&self) -> Quaternion<f32> {
+        self.orientation
+            .unwrap_or_else(|| Self::yaw_pitch_orientation(self.yaw, self.pitch))
+    }
+
+    // Only takes effect in free mode
+    pub fn set_orientation(&mut self, orientation: Quaternion<f32>) {
+        if self.orientation.is_some() {
+            self.orientation = Some(orientation.normalize());
+        }
+    }
+
+    // Pitch about the camera's right axis, then yaw about the world's up axis.
+    fn yaw_pitch_orientation(yaw: Rad<f32>, pitch: Rad<f32>) -> Quaternion<f32> {
+        Quaternion::from_angle_y(-yaw) * Quaternion::from_angle_z(pitch)
+    }
+
+    pub fn forward(&self) -> Vector3<f32> {
+        match self.orientation {
+            Some(q) => q.rotate_vector(Vector3::unit_x()),
+            None => {
+                let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
+                let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
+
+                Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize()
+            }
+        }
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        match self.orientation {
+            Some(q) => q.rotate_vector(Vector3::unit_y()),
+            None => self.right().cross(self.forward()),
+        }
+    }
 
-        Matrix4::look_to_rh(
-            self.position,
-            Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize(),
-            Vector3::unit_y(),
-        )
+    pub fn right(&self) -> Vector3<f32> {
+        match self.orientation {
+            Some(q) => q.rotate_vector(Vector3::unit_z()),
+            None => self.forward().cross(Vector3::unit_y()).normalize(),
+        }
+    }
+
+    // Turns the camera like mouse look does. In free mode the rotation is about
+    // the camera's own axes, so it keeps working when upside down.
+    pub fn turn(&mut self, yaw: Rad<f32>, pitch: Rad<f32>) {
+        match self.orientation {
+            Some(q) => {
+                let q = q * Quaternion::from_angle_y(-yaw) * Quaternion::from_angle_z(pitch);
+                self.orientation = Some(q.normalize());
+            }
+            None => {
+                self.yaw += yaw;
+                self.pitch += pitch;
+                self.clamp_pitch();
+            }
+        }
+    }
+
+    // Roll about the view direction. Only takes effect in free mode.
+    pub fn roll(&mut self, angle: Rad<f32>) {
+        if let Some(q) = self.orientation {
+            self.orientation = Some((q * Quaternion::from_angle_x(angle)).normalize());
+        }
+    }
+
+    pub fn calc_matrix(&self) -> Matrix4<f32> {
+        let up = match self.orientation {
+            Some(_) => self.up(),
+            None => Vector3::unit_y(),
+        };
+        Matrix4::look_to_rh(self.position, self.forward(), up)
     }
 
     pub fn calc_inv_matrix(&self) -> Matrix4<f32> {
-        let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
-        let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
+        self.calc_matrix().invert().unwrap()
+    }
 
-        Matrix4::look_to_rh(
-            self.position,
-            Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize(),
-            Vector3::unit_y(),
-        ).invert().unwrap()
+    // Keep the camera's angle from going too high/low.
+    fn clamp_pitch(&mut self) {
+        if self.pitch < -Rad(SAFE_FRAC_PI_2) {
+            self.pitch = -Rad(SAFE_FRAC_PI_2);
+        } else if self.pitch > Rad(SAFE_FRAC_PI_2) {
+            self.pitch = Rad(SAFE_FRAC_PI_2);
+        }
     }
 }
 
@@ -61,6 +196,16 @@ impl Camera {
 pub struct CameraUniform {
     view_position: [f32; 4],
     view_proj: [[f32; 4]; 4],
+    projection: [f32; 4], // fovy (radians), aspect, znear, zfar
+    mode: u32,            // 0 = perspective, 1 = orthographic
+    ortho_extent: f32,
+    _padding: [f32; 2],
+}
+
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CameraUniform {
@@ -68,20 +213,40 @@ impl CameraUniform {
         Self {
             view_position: [0.0; 4],
             view_proj: Matrix4::identity().into(),
+            projection: [0.0; 4],
+            mode: 0,
+            ortho_extent: 0.0,
+            _padding: [0.0; 2],
         }
     }
 
-    pub fn update_view_proj(&mut self, camera: &Camera, _projection: &Projection) {
+    pub fn update_view_proj(&mut self, camera: &Camera, projection: &Projection) {
         self.view_position = camera.position.to_homogeneous().into();
-        self.view_proj = (camera.calc_matrix()).into()
+        self.view_proj = (camera.calc_matrix()).into();
+        self.projection = [
+            projection.fovy.0,
+            projection.aspect,
+            projection.znear,
+            projection.zfar,
+        ];
+        self.mode = projection.mode as u32;
+        self.ortho_extent = projection.ortho_extent;
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProjectionMode {
+    Perspective = 0,
+    Orthographic = 1,
+}
+
 pub struct Projection {
     aspect: f32,
     fovy: Rad<f32>,
     znear: f32,
     zfar: f32,
+    mode: ProjectionMode,
+    ortho_extent: f32, // half the height of the orthographic view, in world units
 }
 
 impl Projection {
@@ -91,6 +256,8 @@ impl Projection {
             fovy: fovy.into(),
             znear,
             zfar,
+            mode: ProjectionMode::Perspective,
+            ortho_extent: 5.0,
         }
     }
 
@@ -98,15 +265,72 @@ impl Projection {
         self.aspect = width as f32 / height as f32;
     }
 
+    pub fn aspect(&self) -> f32 {
+        self.aspect
+    }
+
+    pub fn fovy(&self) -> Rad<f32> {
+        self.fovy
+    }
+
+    pub fn set_fovy<F: Into<Rad<f32>>>(&mut self, fovy: F) {
+        self.fovy = Rad(fovy.into().0.clamp(MIN_FOVY, MAX_FOVY));
+    }
+
+    pub fn mode(&self) -> ProjectionMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: ProjectionMode) {
+        self.mode = mode;
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            ProjectionMode::Perspective => ProjectionMode::Orthographic,
+            ProjectionMode::Orthographic => ProjectionMode::Perspective,
+        };
+    }
+
+    pub fn ortho_extent(&self) -> f32 {
+        self.ortho_extent
+    }
+
+    pub fn set_ortho_extent(&mut self, extent: f32) {
+        self.ortho_extent = extent.clamp(MIN_ORTHO_EXTENT, MAX_ORTHO_EXTENT);
+    }
+
+    pub fn znear(&self) -> f32 {
+        self.znear
+    }
+
+    pub fn zfar(&self) -> f32 {
+        self.zfar
+    }
+
     pub fn calc_matrix(&self) -> Matrix4<f32> {
-        perspective(self.fovy, self.aspect, self.znear, self.zfar)
+        match self.mode {
+            ProjectionMode::Perspective => {
+                perspective(self.fovy, self.aspect, self.znear, self.zfar)
+            }
+            ProjectionMode::Orthographic => {
+                let (h, w) = (self.ortho_extent, self.ortho_extent * self.aspect);
+                ortho(-w, w, -h, h, self.znear, self.zfar)
+            }
+        }
     }
 
     pub fn calc_matrix_wgpu(&self) -> Matrix4<f32> {
-        OPENGL_TO_WGPU_MATRIX * perspective(self.fovy, self.aspect, self.znear, self.zfar)
+        OPENGL_TO_WGPU_MATRIX * self.calc_matrix()
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+    Fly,
+    Orbit,
+}
+
 #[derive(Debug)]
 pub struct CameraController {
     amount_left: f32,
@@ -117,9 +341,22 @@ pub struct CameraController {
     amount_down: f32,
     rotate_horizontal: f32,
     rotate_vertical: f32,
+    pan_horizontal: f32,
+    pan_vertical: f32,
     scroll: f32,
+    amount_zoom_in: f32,
+    amount_zoom_out: f32,
+    amount_roll_left: f32,
+    amount_roll_right: f32,
+    toggle_projection: bool,
+    toggle_free: bool,
+    toggle_mode: bool,
     speed: f32,
     sensitivity: f32,
+    zoom_speed: f32,
+    mode: CameraMode,
+    orbit_target: Point3<f32>,
+    orbit_distance: f32,
 }
 
 impl CameraController {
@@ -133,43 +370,148 @@ impl CameraController {
             amount_down: 0.0,
             rotate_horizontal: 0.0,
             rotate_vertical: 0.0,
+            pan_horizontal: 0.0,
+            pan_vertical: 0.0,
             scroll: 0.0,
+            amount_zoom_in: 0.0,
+            amount_zoom_out: 0.0,
+            amount_roll_left: 0.0,
+            amount_roll_right: 0.0,
+            toggle_projection: false,
+            toggle_free: false,
+            toggle_mode: false,
             speed,
             sensitivity,
+            zoom_speed: 0.5,
+            mode: CameraMode::Fly,
+            orbit_target: Point3::new(0.0, 0.0, 0.0),
+            orbit_distance: 10.0,
+        }
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    // Switching to orbit picks a target straight ahead of the camera at the
+    // current orbit distance, so neither direction moves the view.
+    pub fn set_mode(&mut self, mode: CameraMode, camera: &Camera) {
+        if mode == CameraMode::Orbit && self.mode != CameraMode::Orbit {
+            self.orbit_target = camera.position + camera.forward() * self.orbit_distance;
+        }
+        self.mode = mode;
+    }
+
+    // Re-derives the orbit target after the camera was moved from outside the
+    // controller, e.g. by a camera path.
+    pub fn sync(&mut self, camera: &Camera) {
+        if self.mode == CameraMode::Orbit {
+            self.orbit_target = camera.position + camera.forward() * self.orbit_distance;
         }
     }
 
-    pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+    pub fn orbit_target(&self) -> Point3<f32> {
+        self.orbit_target
+    }
+
+    pub fn orbit_distance(&self) -> f32 {
+        self.orbit_distance
+    }
+
+    // Moves the camera back along its current view direction until the box
+    // fits on screen, and orbits around its centre from then on.
+    pub fn frame(
+        &mut self,
+        camera: &mut Camera,
+        projection: &mut Projection,
+        bounds: (Vector3<f32>, Vector3<f32>),
+    ) {
+        let (min, max) = bounds;
+        let center = Point3::from_vec((min + max) / 2.0);
+        let radius = ((max - min).magnitude() / 2.0).max(MIN_ORBIT_DISTANCE);
+
+        self.orbit_distance = match projection.mode() {
+            ProjectionMode::Perspective => {
+                // fit the bounding sphere in the narrower of the two fields of view
+                let half_fovy = projection.fovy().0 / 2.0;
+                let half_fovx = (half_fovy.tan() * projection.aspect()).atan();
+                radius / half_fovy.min(half_fovx).sin()
+            }
+            ProjectionMode::Orthographic => {
+                projection.set_ortho_extent(radius / projection.aspect().min(1.0));
+                radius * 2.0 + projection.znear()
+            }
+        };
+        self.orbit_target = center;
+        self.mode = CameraMode::Orbit;
+        camera.position = self.orbit_target - camera.forward() * self.orbit_distance;
+    }
+
+    pub fn process_action(&mut self, action: Action, state: ElementState) -> bool {
         let amount = if state == ElementState::Pressed {
             1.0
         } else {
             0.0
         };
-        match key {
-            VirtualKeyCode::W | VirtualKeyCode::Up => {
+        match action {
+            Action::MoveForward => {
                 self.amount_forward = amount;
                 true
             }
-            VirtualKeyCode::S | VirtualKeyCode::Down => {
+            Action::MoveBackward => {
                 self.amount_backward = amount;
                 true
             }
-            VirtualKeyCode::A | VirtualKeyCode::Left => {
+            Action::MoveLeft => {
                 self.amount_left = amount;
                 true
             }
-            VirtualKeyCode::D | VirtualKeyCode::Right => {
+            Action::MoveRight => {
                 self.amount_right = amount;
                 true
             }
-            VirtualKeyCode::Space => {
+            Action::MoveUp => {
                 self.amount_up = amount;
                 true
             }
-            VirtualKeyCode::LShift => {
+            Action::MoveDown => {
                 self.amount_down = amount;
                 true
             }
+            Action::ZoomIn => {
+                self.amount_zoom_in = amount;
+                true
+            }
+            Action::ZoomOut => {
+                self.amount_zoom_out = amount;
+                true
+            }
+            Action::ToggleProjection => {
+                if state == ElementState::Pressed {
+                    self.toggle_projection = true;
+                }
+                true
+            }
+            Action::RollLeft => {
+                self.amount_roll_left = amount;
+                true
+            }
+            Action::RollRight => {
+                self.amount_roll_right = amount;
+                true
+            }
+            Action::ToggleFreeCamera => {
+                if state == ElementState::Pressed {
+                    self.toggle_free = true;
+                }
+                true
+            }
+            Action::ToggleCameraMode => {
+                if state == ElementState::Pressed {
+                    self.toggle_mode = true;
+                }
+                true
+            }
             _ => false,
         }
     }
@@ -179,6 +521,11 @@ impl CameraController {
         self.rotate_vertical = mouse_dy as f32;
     }
 
+    pub fn process_pan(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        self.pan_horizontal = -mouse_dx as f32;
+        self.pan_vertical = mouse_dy as f32;
+    }
+
     pub fn process_scroll(&mut self, delta: &MouseScrollDelta) {
         self.scroll = match delta {
             // I'm assuming a line is about 100 pixels
@@ -188,12 +535,42 @@ impl CameraController {
     }
 
     pub fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
+        if self.toggle_mode {
+            let mode = match self.mode {
+                CameraMode::Fly => CameraMode::Orbit,
+                CameraMode::Orbit => CameraMode::Fly,
+            };
+            self.set_mode(mode, camera);
+            self.toggle_mode = false;
+        }
+        if self.toggle_free {
+            camera.set_free(!camera.is_free());
+            self.toggle_free = false;
+        }
+
+        let roll = (self.amount_roll_right - self.amount_roll_left) * self.sensitivity * 4.0;
+        camera.roll(Rad(roll * dt.as_secs_f32()));
+
+        match self.mode {
+            CameraMode::Fly => self.update_fly(camera, dt),
+            CameraMode::Orbit => self.update_orbit(camera, dt),
+        }
+    }
+
+    fn update_fly(&mut self, camera: &mut Camera, dt: Duration) {
         let dt = dt.as_secs_f32();
 
-        // Move forward/backward and left/right
-        let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
-        let forward = Vector3::new(yaw_cos, 0.0, yaw_sin).normalize();
-        let right = Vector3::new(-yaw_sin, 0.0, yaw_cos).normalize();
+        // Move forward/backward and left/right. A free camera moves along its
+        // own axes instead of staying level.
+        let (forward, right) = if camera.is_free() {
+            (camera.forward(), camera.right())
+        } else {
+            let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
+            (
+                Vector3::new(yaw_cos, 0.0, yaw_sin).normalize(),
+                Vector3::new(-yaw_sin, 0.0, yaw_cos).normalize(),
+            )
+        };
         camera.position += forward * (self.amount_forward - self.amount_backward) * self.speed * dt;
         camera.position += right * (self.amount_right - self.amount_left) * self.speed * dt;
 
@@ -201,31 +578,92 @@ impl CameraController {
         // Note: this isn't an actual zoom. The camera's position
         // changes when zooming. I've added this to make it easier
         // to get closer to an object you want to focus on.
-        let (pitch_sin, pitch_cos) = camera.pitch.0.sin_cos();
-        let scrollward =
-            Vector3::new(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin).normalize();
+        let scrollward = camera.forward();
         camera.position += scrollward * self.scroll * self.speed * self.sensitivity * dt;
         self.scroll = 0.0;
 
-        // Move up/down. Since we don't use roll, we can just
-        // modify the y coordinate directly.
-        camera.position.y -= (self.amount_up - self.amount_down) * self.speed * dt;
+        // Move up/down. Without roll we can just modify the y coordinate
+        // directly, a free camera moves along its own up axis.
+        if camera.is_free() {
+            camera.position -= camera.up() * (self.amount_up - self.amount_down) * self.speed * dt;
+        } else {
+            camera.position.y -= (self.amount_up - self.amount_down) * self.speed * dt;
+        }
 
         // Rotate
-        camera.yaw += Rad(self.rotate_horizontal) * self.sensitivity * dt;
-        camera.pitch += Rad(-self.rotate_vertical) * self.sensitivity * dt;
+        camera.turn(
+            Rad(self.rotate_horizontal) * self.sensitivity * dt,
+            Rad(-self.rotate_vertical) * self.sensitivity * dt,
+        );
 
         // If process_mouse isn't called every frame, these values
         // will not get set to zero, and the camera will rotate
         // when moving in a non cardinal direction.
         self.rotate_horizontal = 0.0;
         self.rotate_vertical = 0.0;
+        self.pan_horizontal = 0.0;
+        self.pan_vertical = 0.0;
+    }
+
+    fn update_orbit(&mut self, camera: &mut Camera, dt: Duration) {
+        let dt = dt.as_secs_f32();
+
+        // Revolve around the target
+        camera.turn(
+            Rad(self.rotate_horizontal) * self.sensitivity * dt,
+            Rad(-self.rotate_vertical) * self.sensitivity * dt,
+        );
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+
+        // Dolly in/out. This scales the distance so it never passes the target.
+        let dolly = (1.0 - self.scroll * self.sensitivity * dt * 0.1).max(0.1);
+        self.orbit_distance = (self.orbit_distance * dolly).max(MIN_ORBIT_DISTANCE);
+        self.scroll = 0.0;
 
-        // Keep the camera's angle from going too high/low.
-        if camera.pitch < -Rad(SAFE_FRAC_PI_2) {
-            camera.pitch = -Rad(SAFE_FRAC_PI_2);
-        } else if camera.pitch > Rad(SAFE_FRAC_PI_2) {
-            camera.pitch = Rad(SAFE_FRAC_PI_2);
+        // Pan the target across the view plane. Scaling by the distance keeps
+        // the target moving at roughly the speed of the cursor.
+        let forward = camera.forward();
+        let right = camera.right();
+        let up = camera.up();
+        let pan = self.sensitivity * dt * self.orbit_distance;
+        self.orbit_target += right * self.pan_horizontal * pan;
+        self.orbit_target += up * self.pan_vertical * pan;
+        self.pan_horizontal = 0.0;
+        self.pan_vertical = 0.0;
+
+        // Keyboard movement carries the target along like the fly camera
+        let (yaw_sin, yaw_cos) = camera.yaw().0.sin_cos();
+        let forward_flat = Vector3::new(yaw_cos, 0.0, yaw_sin).normalize();
+        let right_flat = Vector3::new(-yaw_sin, 0.0, yaw_cos).normalize();
+        self.orbit_target +=
+            forward_flat * (self.amount_forward - self.amount_backward) * self.speed * dt;
+        self.orbit_target += right_flat * (self.amount_right - self.amount_left) * self.speed * dt;
+        self.orbit_target.y -= (self.amount_up - self.amount_down) * self.speed * dt;
+
+        camera.position = self.orbit_target - forward * self.orbit_distance;
+    }
+
+    // Zooms by narrowing or widening the field of view (or the orthographic
+    // extent), unlike scrolling which moves the camera.
+    pub fn update_projection(&mut self, projection: &mut Projection, dt: Duration) {
+        if self.toggle_projection {
+            projection.toggle_mode();
+            self.toggle_projection = false;
+        }
+
+        let dt = dt.as_secs_f32();
+        let zoom = (self.amount_zoom_out - self.amount_zoom_in) * self.zoom_speed * dt;
+        if zoom != 0.0 {
+            match projection.mode() {
+                ProjectionMode::Perspective => {
+                    projection.set_fovy(projection.fovy() + Rad(zoom))
+                }
+                ProjectionMode::Orthographic => {
+                    // scale the extent so zooming feels the same at any size
+                    projection.set_ortho_extent(projection.ortho_extent() * (1.0 + zoom))
+                }
+            }
         }
     }
 }
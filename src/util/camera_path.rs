@@ -0,0 +1,223 @@
+use cgmath::{Point3, Quaternion, Rad};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{PI, TAU};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::camera::{Camera, Projection};
+
+// Seconds between keyframes captured with `CameraPath::capture`
+pub const KEYFRAME_SPACING: f32 = 2.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub time: f32,
+    pub position: [f32; 3],
+    pub yaw: f32,   // radians
+    pub pitch: f32, // radians
+    pub fovy: f32,  // radians
+    // x, y, z, w of a free camera's orientation, overrides yaw and pitch
+    #[serde(default)]
+    pub orientation: Option<[f32; 4]>,
+}
+
+impl Keyframe {
+    pub fn from_camera(camera: &Camera, projection: &Projection, time: f32) -> Self {
+        Self {
+            time,
+            position: camera.position.into(),
+            yaw: camera.yaw().0,
+            pitch: camera.pitch().0,
+            fovy: projection.fovy().0,
+            orientation: camera.is_free().then(|| {
+                let q = camera.orientation();
+                [q.v.x, q.v.y, q.v.z, q.s]
+            }),
+        }
+    }
+
+    pub fn apply(&self, camera: &mut Camera, projection: &mut Projection) {
+        camera.position = Point3::from(self.position);
+        match self.orientation {
+            Some([x, y, z, w]) => {
+                camera.set_free(true);
+                camera.set_orientation(Quaternion::new(w, x, y, z));
+            }
+            None => camera.set_yaw_pitch(Rad(self.yaw), Rad(self.pitch)),
+        }
+        projection.set_fovy(Rad(self.fovy));
+    }
+
+    fn values(&self) -> [f32; 6] {
+        [
+            self.position[0],
+            self.position[1],
+            self.position[2],
+            self.yaw,
+            self.pitch,
+            self.fovy,
+        ]
+    }
+
+    fn from_values(time: f32, v: [f32; 6]) -> Self {
+        Self {
+            time,
+            position: [v[0], v[1], v[2]],
+            yaw: v[3],
+            pitch: v[4],
+            fovy: v[5],
+            orientation: None,
+        }
+    }
+}
+
+// A camera fly-through. Keyframes are kept sorted by time and interpolated
+// with a Catmull-Rom spline, so the camera passes through every keyframe.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub fn new() -> Self {
+        Self { keyframes: vec![] }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut camera_path: CameraPath =
+            ron::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        camera_path
+            .keyframes
+            .sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(camera_path)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+
+    // Inserts a keyframe, replacing any existing keyframe at the same time.
+    pub fn add_keyframe(&mut self, keyframe: Keyframe) {
+        match self
+            .keyframes
+            .binary_search_by(|k| k.time.total_cmp(&keyframe.time))
+        {
+            Ok(i) => self.keyframes[i] = keyframe,
+            Err(i) => self.keyframes.insert(i, keyframe),
+        }
+    }
+
+    // Appends the camera's current view KEYFRAME_SPACING seconds after the
+    // last keyframe.
+    pub fn capture(&mut self, camera: &Camera, projection: &Projection) {
+        let time = self
+            .keyframes
+            .last()
+            .map_or(0.0, |k| k.time + KEYFRAME_SPACING);
+        self.add_keyframe(Keyframe::from_camera(camera, projection, time));
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn len(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn start_time(&self) -> f32 {
+        self.keyframes.first().map_or(0.0, |k| k.time)
+    }
+
+    pub fn duration(&self) -> f32 {
+        match (self.keyframes.first(), self.keyframes.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+
+    // Interpolated camera state at `time`, clamped to the ends of the path.
+    pub fn sample(&self, time: f32) -> Option<Keyframe> {
+        let k = &self.keyframes;
+        let first = k.first()?;
+        let last = k.last()?;
+        if time <= first.time {
+            return Some(Keyframe { time, ..*first });
+        }
+        if time >= last.time {
+            return Some(Keyframe { time, ..*last });
+        }
+
+        // segment between k[i] and k[i + 1], with neighbours clamped at the ends
+        let i = k.partition_point(|a| a.time <= time) - 1;
+        let indices = [i.saturating_sub(1), i, i + 1, (i + 2).min(k.len() - 1)];
+        let [k0, k1, k2, k3] = indices.map(|j| &k[j]);
+
+        let span = k2.time - k1.time;
+        let u = (time - k1.time) / span;
+        let (u2, u3) = (u * u, u * u * u);
+        let h00 = 2.0 * u3 - 3.0 * u2 + 1.0;
+        let h10 = u3 - 2.0 * u2 + u;
+        let h01 = -2.0 * u3 + 3.0 * u2;
+        let h11 = u3 - u2;
+
+        // Catmull-Rom tangents, scaled for keyframes that are not evenly spaced
+        let [mut v0, mut v1, mut v2, mut v3] = [k0, k1, k2, k3].map(Keyframe::values);
+        let yaws = self.unwrapped_yaws();
+        for (v, j) in [&mut v0, &mut v1, &mut v2, &mut v3].into_iter().zip(indices) {
+            v[3] = yaws[j];
+        }
+        let mut values = [0.0; 6];
+        for j in 0..6 {
+            let m1 = (v2[j] - v0[j]) / (k2.time - k0.time) * span;
+            let m2 = (v3[j] - v1[j]) / (k3.time - k1.time) * span;
+            values[j] = h00 * v1[j] + h10 * m1 + h01 * v2[j] + h11 * m2;
+        }
+        let mut keyframe = Keyframe::from_values(time, values);
+
+        // Orientations are slerped between the two keyframes when both have one
+        if let (Some([x1, y1, z1, w1]), Some([x2, y2, z2, w2])) = (k1.orientation, k2.orientation) {
+            let q = Quaternion::new(w1, x1, y1, z1).slerp(Quaternion::new(w2, x2, y2, z2), u);
+            keyframe.orientation = Some([q.v.x, q.v.y, q.v.z, q.s]);
+        }
+        Some(keyframe)
+    }
+
+    // Keyframe yaws, each moved by whole turns to within half a turn of the
+    // previous one, so the camera turns the short way from 350° to 10°.
+    fn unwrapped_yaws(&self) -> Vec<f32> {
+        let mut yaws: Vec<f32> = Vec::with_capacity(self.keyframes.len());
+        for k in &self.keyframes {
+            let yaw = match yaws.last() {
+                Some(previous) => previous + (k.yaw - previous + PI).rem_euclid(TAU) - PI,
+                None => k.yaw,
+            };
+            yaws.push(yaw);
+        }
+        yaws
+    }
+
+    // Moves the camera to where the path is at `time`. Returns false once the
+    // path has finished (or is empty).
+    pub fn apply(&self, time: f32, camera: &mut Camera, projection: &mut Projection) -> bool {
+        match self.sample(time) {
+            Some(keyframe) => {
+                keyframe.apply(camera, projection);
+                time < self.keyframes.last().unwrap().time
+            }
+            None => false,
+        }
+    }
+}
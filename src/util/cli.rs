@@ -0,0 +1,314 @@
+use std::path::PathBuf;
+
+use wgpu::PresentMode;
+
+use super::adapter::{self, AdapterOptions};
+use super::recorder::RecordFormat;
+use super::scene::CameraDesc;
+use super::terminal::TerminalStyle;
+use crate::Quality;
+
+pub const USAGE: &str = "\
+Usage:
+    ray_marching [OPTIONS]                    open a window
+    ray_marching render --out FILE [OPTIONS]  render one frame to a PNG without a window,
+                                              on the CPU when no GPU adapter is found
+    ray_marching validate SCENE               check a scene file for problems
+
+Scene options:
+    --scene FILE            RON scene to load instead of the demo scene
+    --quality PRESET        low, medium, high or ultra (default medium)
+
+GPU options:
+    --backend NAME          vulkan, metal, dx12, dx11, gl, webgpu, primary or all
+    --adapter NAME          use the adapter whose name contains NAME
+    --software              use a software adapter
+
+Window options:
+    --size WxH              window size in pixels
+    --fullscreen            borderless fullscreen on the current monitor
+    --present-mode MODE     fifo, fifo-relaxed, mailbox, immediate, auto-vsync
+                            or auto-no-vsync (default fifo)
+    --record-format FORMAT  png, y4m or gif, used by the record key
+    --screenshot-scale N    render screenshots at N times the window size
+                            (default 4, limited by the GPU's texture size)
+    --list-adapters         print the adapters on the chosen backends and exit
+    --terminal[=STYLE]      render on the CPU into the terminal, STYLE is
+                            truecolor or ramp (default detected)
+
+Render options:
+    --out FILE              PNG to write
+    --size WxH              image size (default 1280x720)
+    --camera X,Y,Z[,YAW,PITCH[,FOVY]]
+                            camera position and angles in degrees
+    --time SECONDS          scene time to render at (default 0)
+";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Window(WindowOptions),
+    Terminal {
+        style: TerminalStyle,
+        scene: Option<PathBuf>,
+    },
+    ListAdapters(wgpu::Backends),
+    Render(RenderOptions),
+    Validate(PathBuf),
+    Help,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowOptions {
+    pub scene: Option<PathBuf>,
+    pub size: Option<(u32, u32)>,
+    pub fullscreen: bool,
+    pub present_mode: PresentMode,
+    pub quality: Quality,
+    pub record_format: RecordFormat,
+    // multiple of the window size screenshots are rendered at
+    pub screenshot_scale: u32,
+    pub adapter: AdapterOptions,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            scene: None,
+            size: None,
+            fullscreen: false,
+            present_mode: PresentMode::Fifo,
+            quality: Quality::Medium,
+            record_format: RecordFormat::PngSequence,
+            screenshot_scale: 4,
+            adapter: AdapterOptions::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderOptions {
+    pub scene: Option<PathBuf>,
+    pub out: PathBuf,
+    pub size: (u32, u32),
+    // the scene's or the default camera if None
+    pub camera: Option<CameraDesc>,
+    pub time: f32,
+    pub quality: Quality,
+    pub adapter: AdapterOptions,
+}
+
+// Splits `--name=value` and `--name value` arguments.
+struct Args<I: Iterator<Item = String>> {
+    args: std::iter::Peekable<I>,
+    // value given with `=` to the last option
+    inline_value: Option<String>,
+}
+
+impl<I: Iterator<Item = String>> Args<I> {
+    fn next_option(&mut self) -> Result<Option<String>, String> {
+        if let Some(value) = self.inline_value.take() {
+            return Err(format!("unexpected value '{}'", value));
+        }
+        let Some(arg) = self.args.next() else {
+            return Ok(None);
+        };
+        match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => {
+                self.inline_value = Some(value.to_string());
+                Ok(Some(name.to_string()))
+            }
+            _ => Ok(Some(arg)),
+        }
+    }
+
+    fn value(&mut self, option: &str) -> Result<String, String> {
+        self.inline_value
+            .take()
+            .or_else(|| self.args.next())
+            .ok_or_else(|| format!("{} needs a value", option))
+    }
+
+    fn optional_value(&mut self) -> Option<String> {
+        self.inline_value.take()
+    }
+}
+
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = Args {
+        args: args.into_iter().peekable(),
+        inline_value: None,
+    };
+    match args.args.peek().map(String::as_str) {
+        Some("render") => {
+            args.args.next();
+            parse_render(&mut args)
+        }
+        Some("validate") => {
+            args.args.next();
+            let scene = args.args.next().ok_or("validate needs a scene file")?;
+            match args.args.next() {
+                Some(extra) => Err(format!("unexpected argument '{}'", extra)),
+                None => Ok(Command::Validate(scene.into())),
+            }
+        }
+        _ => parse_window(&mut args),
+    }
+}
+
+fn parse_window<I: Iterator<Item = String>>(args: &mut Args<I>) -> Result<Command, String> {
+    let mut options = WindowOptions::default();
+    let mut terminal = None;
+    let mut list_adapters = false;
+    while let Some(option) = args.next_option()? {
+        match option.as_str() {
+            "--scene" => options.scene = Some(args.value(&option)?.into()),
+            "--quality" => options.quality = parse_quality(&args.value(&option)?)?,
+            "--size" => options.size = Some(parse_size(&args.value(&option)?)?),
+            "--fullscreen" => options.fullscreen = true,
+            "--present-mode" => options.present_mode = parse_present_mode(&args.value(&option)?)?,
+            "--record-format" => {
+                options.record_format = parse_record_format(&args.value(&option)?)?
+            }
+            "--screenshot-scale" => {
+                options.screenshot_scale = parse_scale(&option, &args.value(&option)?)?
+            }
+            "--backend" => {
+                options.adapter.backends =
+                    adapter::parse_backends(&args.value(&option)?).map_err(|e| e.to_string())?
+            }
+            "--adapter" => options.adapter.name = Some(args.value(&option)?),
+            "--software" => options.adapter.software = true,
+            "--list-adapters" => list_adapters = true,
+            "--terminal" => {
+                terminal = Some(match args.optional_value().as_deref() {
+                    None => TerminalStyle::detect(),
+                    Some("truecolor") => TerminalStyle::TrueColor,
+                    Some("ramp") => TerminalStyle::Ramp,
+                    Some(style) => return Err(format!("unknown terminal style '{}'", style)),
+                })
+            }
+            "--help" | "-h" => return Ok(Command::Help),
+            _ => return Err(format!("unknown option '{}'", option)),
+        }
+    }
+
+    Ok(if list_adapters {
+        Command::ListAdapters(options.adapter.backends)
+    } else if let Some(style) = terminal {
+        Command::Terminal {
+            style,
+            scene: options.scene,
+        }
+    } else {
+        Command::Window(options)
+    })
+}
+
+fn parse_render<I: Iterator<Item = String>>(args: &mut Args<I>) -> Result<Command, String> {
+    let mut scene = None;
+    let mut out = None;
+    let mut size = (1280, 720);
+    let mut camera = None;
+    let mut time = 0.0;
+    let mut quality = Quality::Medium;
+    let mut adapter = AdapterOptions::default();
+    while let Some(option) = args.next_option()? {
+        match option.as_str() {
+            "--scene" => scene = Some(args.value(&option)?.into()),
+            "--out" | "-o" => out = Some(args.value(&option)?.into()),
+            "--size" => size = parse_size(&args.value(&option)?)?,
+            "--camera" => camera = Some(parse_camera(&args.value(&option)?)?),
+            "--time" => time = parse_number(&option, &args.value(&option)?)?,
+            "--quality" => quality = parse_quality(&args.value(&option)?)?,
+            "--backend" => {
+                adapter.backends =
+                    adapter::parse_backends(&args.value(&option)?).map_err(|e| e.to_string())?
+            }
+            "--adapter" => adapter.name = Some(args.value(&option)?),
+            "--software" => adapter.software = true,
+            "--help" | "-h" => return Ok(Command::Help),
+            _ => return Err(format!("unknown render option '{}'", option)),
+        }
+    }
+    Ok(Command::Render(RenderOptions {
+        scene,
+        out: out.ok_or("render needs --out")?,
+        size,
+        camera,
+        time,
+        quality,
+        adapter,
+    }))
+}
+
+// f32 parsing also takes "NaN" and "inf", which no option can use
+fn parse_number(option: &str, value: &str) -> Result<f32, String> {
+    match value.trim().parse::<f32>() {
+        Ok(number) if number.is_finite() => Ok(number),
+        _ => Err(format!("{} expects a number, not '{}'", option, value)),
+    }
+}
+
+fn parse_scale(option: &str, value: &str) -> Result<u32, String> {
+    match value.trim().parse() {
+        Ok(scale) if scale > 0 => Ok(scale),
+        _ => Err(format!("{} expects a whole number above 0, not '{}'", option, value)),
+    }
+}
+
+pub fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let error = || format!("size should look like 1280x720, not '{}'", value);
+    let (w, h) = value.split_once(['x', 'X']).ok_or_else(error)?;
+    match (w.parse(), h.parse()) {
+        (Ok(w), Ok(h)) if w > 0 && h > 0 => Ok((w, h)),
+        _ => Err(error()),
+    }
+}
+
+// x,y,z then optionally yaw,pitch and fovy in degrees
+pub fn parse_camera(value: &str) -> Result<CameraDesc, String> {
+    let numbers = value
+        .split(',')
+        .map(|v| parse_number("--camera", v))
+        .collect::<Result<Vec<_>, _>>()?;
+    if !matches!(numbers.len(), 3 | 5 | 6) {
+        return Err(format!("--camera expects x,y,z[,yaw,pitch[,fovy]], not '{}'", value));
+    }
+    Ok(CameraDesc {
+        position: [numbers[0], numbers[1], numbers[2]],
+        yaw: numbers.get(3).copied().unwrap_or(0.0),
+        pitch: numbers.get(4).copied().unwrap_or(0.0),
+        fovy: numbers.get(5).copied().unwrap_or(45.0),
+    })
+}
+
+pub fn parse_quality(value: &str) -> Result<Quality, String> {
+    match value.to_lowercase().as_str() {
+        "low" => Ok(Quality::Low),
+        "medium" => Ok(Quality::Medium),
+        "high" => Ok(Quality::High),
+        "ultra" => Ok(Quality::Ultra),
+        _ => Err(format!("unknown quality '{}'", value)),
+    }
+}
+
+pub fn parse_present_mode(value: &str) -> Result<PresentMode, String> {
+    match value.to_lowercase().as_str() {
+        "fifo" | "vsync" => Ok(PresentMode::Fifo),
+        "fifo-relaxed" => Ok(PresentMode::FifoRelaxed),
+        "mailbox" => Ok(PresentMode::Mailbox),
+        "immediate" => Ok(PresentMode::Immediate),
+        "auto-vsync" => Ok(PresentMode::AutoVsync),
+        "auto-no-vsync" => Ok(PresentMode::AutoNoVsync),
+        _ => Err(format!("unknown present mode '{}'", value)),
+    }
+}
+
+pub fn parse_record_format(value: &str) -> Result<RecordFormat, String> {
+    match value.to_lowercase().as_str() {
+        "png" => Ok(RecordFormat::PngSequence),
+        "y4m" => Ok(RecordFormat::Y4m),
+        "gif" => Ok(RecordFormat::Gif),
+        _ => Err(format!("unknown record format '{}'", value)),
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+// Length of a frame step when the clock has no fixed step of its own
+pub const DEFAULT_STEP: Duration = Duration::from_nanos(16_666_667);
+pub const MIN_TIME_SCALE: f32 = 1.0 / 64.0;
+pub const MAX_TIME_SCALE: f32 = 64.0;
+
+// Scene time, decoupled from the wall clock. Everything animated advances by
+// what `tick` returns, so pausing, slowing down or fixing the step applies
+// to all of it at once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clock {
+    time: f64, // seconds, f64 so long sessions don't lose precision
+    paused: bool,
+    scale: f32,
+    fixed_step: Option<Duration>,
+    pending_steps: u32,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            time: 0.0,
+            paused: false,
+            scale: 1.0,
+            fixed_step: None,
+            pending_steps: 0,
+        }
+    }
+
+    // Scene time in seconds
+    pub fn time(&self) -> f32 {
+        self.time as f32
+    }
+
+    // Jumps to `time`, returning how far the clock moved.
+    pub fn seek(&mut self, time: f32) -> f32 {
+        let time = time.max(0.0) as f64;
+        let delta = time - self.time;
+        self.time = time;
+        delta as f32
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.pending_steps = 0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.paused {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.scale
+    }
+
+    // 0.5 is half speed, 2.0 double speed
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+    }
+
+    pub fn fixed_step(&self) -> Option<Duration> {
+        self.fixed_step
+    }
+
+    // With a fixed step every tick advances by exactly `step` (times the time
+    // scale) however much real time passed, for reproducible runs.
+    pub fn set_fixed_step(&mut self, step: Option<Duration>) {
+        self.fixed_step = step;
+    }
+
+    // Length of one frame step
+    pub fn step_length(&self) -> Duration {
+        self.fixed_step.unwrap_or(DEFAULT_STEP)
+    }
+
+    // Pauses and queues one step of `step_length` for the next tick.
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.pending_steps += 1;
+    }
+
+    // Advances the clock for a frame that took `real_dt` and returns the
+    // scene time that passed.
+    pub fn tick(&mut self, real_dt: Duration) -> Duration {
+        let dt = if self.paused {
+            if self.pending_steps == 0 {
+                return Duration::ZERO;
+            }
+            // steps are always one whole frame, whatever the time scale
+            self.pending_steps -= 1;
+            self.step_length()
+        } else {
+            let dt = self.fixed_step.unwrap_or(real_dt);
+            // keep whole steps exact at normal speed
+            if self.scale == 1.0 {
+                dt
+            } else {
+                dt.mul_f64(self.scale as f64)
+            }
+        };
+        self.time += dt.as_secs_f64();
+        dt
+    }
+}
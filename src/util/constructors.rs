@@ -1,37 +1,4 @@
-use wgpu::{
-    Adapter, BindGroup, BindGroupLayout, BindingResource, BufferSize, Device, Instance, Queue,
-    Surface,
-};
-
-pub async fn request_adapter(instance: &Instance, surface: &Surface) -> Adapter {
-    instance
-        .request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::default(),
-            compatible_surface: Some(surface),
-            force_fallback_adapter: false,
-        })
-        .await
-        .unwrap()
-}
-
-pub async fn request_device(adapter: &Adapter) -> (Device, Queue) {
-    adapter
-        .request_device(
-            &wgpu::DeviceDescriptor {
-                features: wgpu::Features::BUFFER_BINDING_ARRAY
-                    | wgpu::Features::STORAGE_RESOURCE_BINDING_ARRAY,
-                limits: if cfg!(target_arch = "wasm32") {
-                    wgpu::Limits::downlevel_webgl2_defaults()
-                } else {
-                    wgpu::Limits::default()
-                },
-                label: None,
-            },
-            None,
-        )
-        .await
-        .unwrap()
-}
+use wgpu::{BindGroup, BindGroupLayout, BindingResource, BufferSize, Device};
 
 pub fn create_bind_group_layout(
     device: &Device,
@@ -0,0 +1,274 @@
+use cgmath::{EuclideanSpace, InnerSpace, Matrix, Matrix3, Vector3};
+
+use super::camera::{Camera, Projection, ProjectionMode};
+use super::shapes::{Flag, Shape, ShapeManager, EPSILON};
+use crate::ShaderParams;
+
+// CPU port of the ray marcher in shader.wgsl, for rendering without a GPU.
+// Keep the two in sync.
+
+// Color of pixels whose ray hits nothing
+pub const BACKGROUND: Vector3<f32> = Vector3::new(0.1, 0.2, 0.3);
+const AMBIENT_LIGHT: f32 = 0.5;
+const SHININESS: f32 = 1000.0;
+// Selection outline, in pixels
+pub const OUTLINE_WIDTH: f32 = 2.0;
+pub const OUTLINE_COLOR: Vector3<f32> = Vector3::new(1.0, 0.6, 0.1);
+
+// A camera ray through one pixel
+pub struct Ray {
+    pub origin: Vector3<f32>,
+    pub dir: Vector3<f32>,
+    // footprint of a pixel at the origin and its growth per unit of depth
+    pub pixel_size: (f32, f32),
+}
+
+impl Ray {
+    // `coord` is in pixels from the top left corner, like the fragment
+    // coordinates in fs_main, so pixel centers are at .5.
+    pub fn through_pixel(
+        camera: &Camera,
+        projection: &Projection,
+        size: (u32, u32),
+        coord: (f32, f32),
+    ) -> Self {
+        let (width, height) = (size.0 as f32, size.1 as f32);
+        let xy = (coord.0 - width / 2.0, coord.1 - height / 2.0);
+        // the shader multiplies vectors from the left, i.e. by the transpose
+        let view = camera.calc_matrix();
+        let view = Matrix3::from_cols(view.x.truncate(), view.y.truncate(), view.z.truncate());
+        let to_world = view.transpose();
+        match projection.mode() {
+            ProjectionMode::Orthographic => {
+                let extent = projection.ortho_extent();
+                let offset = Vector3::new(xy.0, xy.1, 0.0) / (height / 2.0) * extent;
+                Self {
+                    origin: camera.position.to_vec() + to_world * offset,
+                    dir: to_world * Vector3::new(0.0, 0.0, -1.0),
+                    pixel_size: (2.0 * extent / height, 0.0),
+                }
+            }
+            ProjectionMode::Perspective => {
+                let tan = (projection.fovy().0 / 2.0).tan();
+                let z = (height / 2.0) / tan;
+                Self {
+                    origin: camera.position.to_vec(),
+                    dir: to_world * Vector3::new(xy.0, xy.1, -z).normalize(),
+                    pixel_size: (0.0, 2.0 * tan / height),
+                }
+            }
+        }
+    }
+
+    pub fn at(&self, depth: f32) -> Vector3<f32> {
+        self.origin + self.dir * depth
+    }
+}
+
+// Where a ray hit the scene
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hit {
+    pub depth: f32,
+    pub point: Vector3<f32>,
+    pub shape: u32, // index into ShapeManager::get_shape
+}
+
+// The march range the shader uses: the quality settings clipped to the
+// projection's clip planes.
+pub fn march_range(params: &ShaderParams, projection: &Projection) -> (f32, f32) {
+    let (min_dist, max_dist) = params.march_range();
+    (
+        min_dist.max(projection.znear()),
+        max_dist.min(projection.zfar()),
+    )
+}
+
+// Over-relaxed sphere tracing, see shortest_distance_to_surface in the shader.
+pub fn march(
+    shapes: &ShapeManager,
+    ray: &Ray,
+    range: (f32, f32),
+    params: &ShaderParams,
+) -> Option<Hit> {
+    let mut depth = range.0;
+    let mut omega = params.relaxation();
+    let mut prev_radius = 0.0;
+    let mut step_length = 0.0;
+    for _ in 0..params.max_steps() {
+        let point = ray.at(depth);
+        let (dist, shape) = shapes.scene_sdf(point);
+        let radius = dist.abs();
+        let overshot = omega > 1.0 && (radius + prev_radius) < step_length;
+        if overshot {
+            step_length -= omega * step_length;
+            omega = 1.0;
+        } else {
+            step_length = dist * omega;
+        }
+        prev_radius = radius;
+
+        let footprint = (ray.pixel_size.0 + depth * ray.pixel_size.1) * params.footprint_scale();
+        if !overshot && radius < params.epsilon().max(footprint) {
+            // shapes past the shader's 100.0 cut-off are never hit
+            return shape.map(|shape| Hit {
+                depth,
+                point,
+                shape,
+            });
+        }
+        depth += step_length;
+        if depth >= range.1 {
+            return None;
+        }
+    }
+    None
+}
+
+// The shape under the pixel at `coord` (see Ray::through_pixel) and where
+// the ray hits it, for picking shapes with the mouse.
+pub fn pick(
+    shapes: &ShapeManager,
+    camera: &Camera,
+    projection: &Projection,
+    params: &ShaderParams,
+    size: (u32, u32),
+    coord: (f32, f32),
+) -> Option<Hit> {
+    let ray = Ray::through_pixel(camera, projection, size, coord);
+    march(shapes, &ray, march_range(params, projection), params)
+}
+
+// How close `ray` comes to `shape` before `range.1`, in pixels at the depth
+// of the closest approach, or 0.0 if it hits the shape. See
+// selection_distance in the shader.
+pub fn selection_distance(shape: &dyn Shape, ray: &Ray, range: (f32, f32), params: &ShaderParams) -> f32 {
+    let mut depth = range.0;
+    let mut closest = f32::MAX;
+    for _ in 0..params.max_steps() {
+        let dist = shape.sdf(ray.at(depth));
+        if dist < params.epsilon() {
+            return 0.0;
+        }
+        closest = closest.min(dist / (ray.pixel_size.0 + depth * ray.pixel_size.1));
+        depth += dist;
+        if depth >= range.1 {
+            break;
+        }
+    }
+    closest
+}
+
+// Whether the pixel of `ray`, which hit `hit`, is on the selected shape's
+// outline, as in fs_main.
+fn on_outline(shapes: &ShapeManager, ray: &Ray, hit: Option<&Hit>, range: (f32, f32), params: &ShaderParams) -> bool {
+    let Some(selected) = params.selected().and_then(|i| shapes.get_shape(i)) else {
+        return false;
+    };
+    if !params.hud() || !selected.get_flag(Flag::Enabled) {
+        return false;
+    }
+    let depth = match hit {
+        Some(hit) => {
+            let footprint = (ray.pixel_size.0 + hit.depth * ray.pixel_size.1) * params.footprint_scale();
+            // the outline goes around the selected shape, not over it
+            if selected.sdf(hit.point) < params.epsilon().max(footprint) {
+                return false;
+            }
+            hit.depth
+        }
+        None => range.1,
+    };
+    selection_distance(selected, ray, (range.0, depth), params) < OUTLINE_WIDTH
+}
+
+fn approximate_normal(shapes: &ShapeManager, p: Vector3<f32>) -> Vector3<f32> {
+    let d = |offset: Vector3<f32>| shapes.scene_sdf(p + offset).0 - shapes.scene_sdf(p - offset).0;
+    Vector3::new(
+        d(Vector3::unit_x() * EPSILON),
+        d(Vector3::unit_y() * EPSILON),
+        d(Vector3::unit_z() * EPSILON),
+    )
+    .normalize()
+}
+
+// Same curve as rgb_to_srgb in the shader, which applies it to lit colors
+// but not to the background or the selection outline
+pub fn rgb_to_srgb(rgb: Vector3<f32>) -> Vector3<f32> {
+    let f = |c: f32| ((c + 0.055) / 1.055).powf(2.4);
+    Vector3::new(f(rgb.x), f(rgb.y), f(rgb.z))
+}
+
+// Phong lighting of a hit, see phong_illumination in the shader. The result
+// is the color the fragment shader writes, after rgb_to_srgb.
+pub fn shade(shapes: &ShapeManager, hit: &Hit, eye: Vector3<f32>, params: &ShaderParams) -> Vector3<f32> {
+    let k_d = shapes
+        .get_shape(hit.shape)
+        .map_or(BACKGROUND, |shape| shape.get_color());
+    let mut color = k_d * 0.5 * AMBIENT_LIGHT;
+
+    let n = approximate_normal(shapes, hit.point);
+    let (light_pos, intensity) = params.light();
+    let l = (light_pos - hit.point).normalize();
+    let v = (eye - hit.point).normalize();
+    let r = (-l - n * 2.0 * n.dot(-l)).normalize();
+    let (dot_ln, dot_rv) = (l.dot(n), r.dot(v));
+    if dot_ln >= 0.0 {
+        color += k_d * dot_ln * intensity;
+        if dot_rv >= 0.0 {
+            color += Vector3::new(1.0, 1.0, 1.0) * dot_rv.powf(SHININESS) * intensity;
+        }
+    }
+    rgb_to_srgb(color)
+}
+
+// Encodes a channel from render the way an sRGB render target stores the
+// fragment shader's output
+pub fn encode_srgb(c: f32) -> u8 {
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+// Packs pixels from render into RGBA8 bytes matching what the GPU draws
+pub fn to_rgba8(pixels: &[[f32; 3]]) -> Vec<u8> {
+    pixels
+        .iter()
+        .flat_map(|[r, g, b]| [encode_srgb(*r), encode_srgb(*g), encode_srgb(*b), 255])
+        .collect()
+}
+
+// Renders the scene at `width` x `height` pixels, row major, with colors
+// clamped to [0, 1]. These are the values the fragment shader writes, see
+// to_rgba8 for what ends up on screen.
+pub fn render(
+    shapes: &ShapeManager,
+    camera: &Camera,
+    projection: &Projection,
+    params: &ShaderParams,
+    width: u32,
+    height: u32,
+) -> Vec<[f32; 3]> {
+    let range = march_range(params, projection);
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let coord = (x as f32 + 0.5, y as f32 + 0.5);
+            let ray = Ray::through_pixel(camera, projection, (width, height), coord);
+            let hit = march(shapes, &ray, range, params);
+            let color = match &hit {
+                _ if on_outline(shapes, &ray, hit.as_ref(), range, params) => OUTLINE_COLOR,
+                Some(hit) => shade(shapes, hit, ray.origin, params),
+                None => BACKGROUND,
+            };
+            pixels.push([
+                color.x.clamp(0.0, 1.0),
+                color.y.clamp(0.0, 1.0),
+                color.z.clamp(0.0, 1.0),
+            ]);
+        }
+    }
+    pixels
+}
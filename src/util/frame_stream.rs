@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+
+use image::imageops;
+
+use super::image::Frame;
+
+// Frames decoded ahead of the playhead by default
+pub const DEFAULT_DECODE_AHEAD: usize = 64;
+
+struct Cache {
+    frames: HashMap<usize, Arc<Frame>>,
+    playhead: usize,
+    reverse: bool, // the playhead last moved backwards
+    shutdown: bool,
+}
+
+struct Shared {
+    cache: Mutex<Cache>,
+    // notified when the playhead moves, a frame is decoded or on shutdown
+    changed: Condvar,
+}
+
+// Decodes image files on a worker thread, keeping at most `capacity` frames
+// from the playhead onwards in the direction it last moved (wrapping around
+// for looping playback). The render thread never waits on a decode unless it
+// asks to with `wait_frame`.
+pub struct FrameStream {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<()>>,
+    frame_count: usize,
+}
+
+impl FrameStream {
+    // Frames are scaled to `width` x `height` as they are decoded.
+    pub fn new(paths: Vec<PathBuf>, width: u32, height: u32, capacity: usize) -> Self {
+        let frame_count = paths.len();
+        let shared = Arc::new(Shared {
+            cache: Mutex::new(Cache {
+                frames: HashMap::new(),
+                playhead: 0,
+                reverse: false,
+                shutdown: false,
+            }),
+            changed: Condvar::new(),
+        });
+        let worker = (frame_count > 0).then(|| {
+            let shared = shared.clone();
+            let capacity = capacity.clamp(1, frame_count);
+            std::thread::Builder::new()
+                .name("frame_stream".into())
+                .spawn(move || decode_loop(&shared, &paths, width, height, capacity))
+                .expect("could not start the frame decoding thread")
+        });
+        Self {
+            shared,
+            worker,
+            frame_count,
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Cache> {
+        self.shared.cache.lock().unwrap()
+    }
+
+    fn seek(&self, cache: &mut Cache, index: usize) {
+        if cache.playhead != index {
+            // the shorter way round, so looping from the last frame to the
+            // first still counts as forwards
+            let count = self.frame_count;
+            let forward = (index + count - cache.playhead) % count;
+            cache.reverse = count - forward < forward;
+            cache.playhead = index;
+            self.shared.changed.notify_all();
+        }
+    }
+
+    // Moves the playhead to `index` and returns the frame if it has been
+    // decoded already.
+    pub fn frame(&self, index: usize) -> Option<Arc<Frame>> {
+        if index >= self.frame_count {
+            return None;
+        }
+        let mut cache = self.lock();
+        self.seek(&mut cache, index);
+        cache.frames.get(&index).cloned()
+    }
+
+    // Like `frame`, but blocks until the frame is decoded.
+    pub fn wait_frame(&self, index: usize) -> Arc<Frame> {
+        assert!(index < self.frame_count, "frame {} is out of range", index);
+        let mut cache = self.lock();
+        self.seek(&mut cache, index);
+        loop {
+            if let Some(frame) = cache.frames.get(&index) {
+                return frame.clone();
+            }
+            cache = self.shared.changed.wait(cache).unwrap();
+        }
+    }
+
+    // Number of frames currently decoded
+    pub fn cached(&self) -> usize {
+        self.lock().frames.len()
+    }
+
+    // Whether frame `index` is decoded, without moving the playhead
+    pub fn is_cached(&self, index: usize) -> bool {
+        self.lock().frames.contains_key(&index)
+    }
+}
+
+impl Drop for FrameStream {
+    fn drop(&mut self) {
+        self.lock().shutdown = true;
+        self.shared.changed.notify_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn decode_loop(shared: &Shared, paths: &[PathBuf], width: u32, height: u32, capacity: usize) {
+    let count = paths.len();
+    // frame `k` steps from the playhead in the direction of playback
+    let ahead = |playhead: usize, k: usize, reverse: bool| match reverse {
+        false => (playhead + k) % count,
+        true => (playhead + count - k) % count,
+    };
+    let in_window = |index: usize, playhead: usize, reverse: bool| {
+        let steps = match reverse {
+            false => (index + count - playhead) % count,
+            true => (playhead + count - index) % count,
+        };
+        steps < capacity
+    };
+    loop {
+        let index = {
+            let mut cache = shared.cache.lock().unwrap();
+            loop {
+                if cache.shutdown {
+                    return;
+                }
+                let (playhead, reverse) = (cache.playhead.min(count - 1), cache.reverse);
+                cache.frames.retain(|i, _| in_window(*i, playhead, reverse));
+                let next = (0..capacity)
+                    .map(|k| ahead(playhead, k, reverse))
+                    .find(|i| !cache.frames.contains_key(i));
+                if let Some(next) = next {
+                    break next;
+                }
+                cache = shared.changed.wait(cache).unwrap();
+            }
+        };
+
+        let frame = decode(&paths[index], width, height);
+        let mut cache = shared.cache.lock().unwrap();
+        if in_window(index, cache.playhead.min(count - 1), cache.reverse) {
+            cache.frames.insert(index, Arc::new(frame));
+            shared.changed.notify_all();
+        }
+    }
+}
+
+// Unreadable frames are replaced by placeholders so playback keeps going.
+fn decode(path: &Path, width: u32, height: u32) -> Frame {
+    match image::open(path) {
+        Ok(img) => Frame::from_image(img.resize_exact(width, height, imageops::FilterType::Nearest)),
+        Err(e) => {
+            eprintln!("Could not load frame {}: {}", path.display(), e);
+            Frame::placeholder(width, height)
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use cgmath::{EuclideanSpace, InnerSpace, Matrix3, Quaternion, Rad, Rotation3, Vector3};
+
+use super::camera::{Camera, Projection, ProjectionMode};
+use super::cpu_march::Ray;
+use super::shapes::{Cube, Shape, Sphere};
+
+// Length of the arrows and radius of the rings, in pixels
+pub const GIZMO_SIZE: f32 = 80.0;
+// How close in pixels the cursor has to be to grab a handle
+pub const HANDLE_RADIUS: f32 = 8.0;
+// Segments of a rotation ring
+const RING_SEGMENTS: usize = 64;
+const HANDLE_WIDTH: f32 = 3.0;
+const AXIS_COLORS: [egui::Color32; 3] = [
+    egui::Color32::from_rgb(230, 60, 60),
+    egui::Color32::from_rgb(60, 200, 60),
+    egui::Color32::from_rgb(70, 110, 240),
+];
+const ACTIVE_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 220, 60);
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl GizmoMode {
+    pub fn next(self) -> Self {
+        match self {
+            GizmoMode::Translate => GizmoMode::Rotate,
+            GizmoMode::Rotate => GizmoMode::Scale,
+            GizmoMode::Scale => GizmoMode::Translate,
+        }
+    }
+}
+
+// Which axes the translate and rotate handles follow. Scale handles always
+// follow the shape's own axes, which is what its bounds are measured along.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GizmoSpace {
+    #[default]
+    World,
+    Local,
+}
+
+// Increments drags snap to when enabled
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Snapping {
+    pub enabled: bool,
+    pub translate: f32, // world units
+    pub rotate: Rad<f32>,
+    pub scale: f32, // units of a cube's half size or a sphere's radius
+}
+
+impl Default for Snapping {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            translate: 0.25,
+            rotate: Rad(std::f32::consts::PI / 12.0),
+            scale: 0.1,
+        }
+    }
+}
+
+// Projects between the world and the pixels of a window the way the ray
+// marcher does, see Ray::through_pixel.
+pub struct Viewport<'a> {
+    pub camera: &'a Camera,
+    pub projection: &'a Projection,
+    pub size: (u32, u32),
+}
+
+impl Viewport<'_> {
+    pub fn ray(&self, coord: (f32, f32)) -> Ray {
+        Ray::through_pixel(self.camera, self.projection, self.size, coord)
+    }
+
+    // Pixel coordinates of `point`, None if it is behind the camera
+    pub fn project(&self, point: Vector3<f32>) -> Option<(f32, f32)> {
+        let (width, height) = (self.size.0 as f32, self.size.1 as f32);
+        let v = self.to_view(point);
+        let scale = match self.projection.mode() {
+            ProjectionMode::Orthographic => (height / 2.0) / self.projection.ortho_extent(),
+            ProjectionMode::Perspective if -v.z > 1e-4 => {
+                (height / 2.0) / (self.projection.fovy().0 / 2.0).tan() / -v.z
+            }
+            ProjectionMode::Perspective => return None,
+        };
+        Some((v.x * scale + width / 2.0, v.y * scale + height / 2.0))
+    }
+
+    // World units per pixel at the depth of `point`
+    pub fn pixel_size(&self, point: Vector3<f32>) -> f32 {
+        let height = self.size.1 as f32;
+        match self.projection.mode() {
+            ProjectionMode::Orthographic => 2.0 * self.projection.ortho_extent() / height,
+            ProjectionMode::Perspective => {
+                let depth = (-self.to_view(point).z).max(self.projection.znear());
+                depth * 2.0 * (self.projection.fovy().0 / 2.0).tan() / height
+            }
+        }
+    }
+
+    fn to_view(&self, point: Vector3<f32>) -> Vector3<f32> {
+        let view = self.camera.calc_matrix();
+        let view = Matrix3::from_cols(view.x.truncate(), view.y.truncate(), view.z.truncate());
+        view * (point - self.camera.position.to_vec())
+    }
+}
+
+// A handle of the current mode along or around one axis
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Handle {
+    pub mode: GizmoMode,
+    pub axis: usize,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Drag {
+    handle: Handle,
+    center: Vector3<f32>,
+    axis: Vector3<f32>, // in world space
+    // where the cursor grabbed the handle: a distance along `axis` for
+    // arrows and scale handles, a direction from `center` for rings
+    grab: f32,
+    grab_dir: Vector3<f32>,
+    // translation or angle applied so far
+    applied: f32,
+    // the cube's bounds or the sphere's radius when the drag started
+    start_size: Vector3<f32>,
+}
+
+// Translate, rotate and scale handles for the selected shape
+#[derive(Clone, Debug, Default)]
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    pub space: GizmoSpace,
+    pub snapping: Snapping,
+    drag: Option<Drag>,
+}
+
+impl Gizmo {
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    // The handle being dragged or else the one under `coord`
+    pub fn active_handle(
+        &self,
+        view: &Viewport,
+        shape: &dyn Shape,
+        coord: Option<(f32, f32)>,
+    ) -> Option<Handle> {
+        match self.drag {
+            Some(drag) => Some(drag.handle),
+            None => coord.and_then(|coord| self.hit(view, shape, coord)),
+        }
+    }
+
+    // World space directions of the handles of the current mode
+    pub fn axes(&self, shape: &dyn Shape) -> [Vector3<f32>; 3] {
+        let local = self.mode == GizmoMode::Scale || self.space == GizmoSpace::Local;
+        // shapes rotate sample points into their own space, so their axes
+        // are rotated back
+        let rotation = match local {
+            true => shape.get_rotation().conjugate(),
+            false => Quaternion::new(1.0, 0.0, 0.0, 0.0),
+        };
+        [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()].map(|axis| rotation * axis)
+    }
+
+    // The handle closest to `coord`, if any is within HANDLE_RADIUS
+    pub fn hit(&self, view: &Viewport, shape: &dyn Shape, coord: (f32, f32)) -> Option<Handle> {
+        let cursor = egui::pos2(coord.0, coord.1);
+        (0..3)
+            .filter_map(|axis| {
+                let handle = Handle {
+                    mode: self.mode,
+                    axis,
+                };
+                let distance = self
+                    .handle_outline(view, shape, handle)?
+                    .windows(2)
+                    .map(|segment| segment_distance(cursor, segment[0], segment[1]))
+                    .fold(f32::MAX, f32::min);
+                Some((handle, distance))
+            })
+            .filter(|(_, distance)| *distance <= HANDLE_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(handle, _)| handle)
+    }
+
+    // Starts dragging the handle under `coord`, false if there is none
+    pub fn begin_drag(&mut self, view: &Viewport, shape: &dyn Shape, coord: (f32, f32)) -> bool {
+        let Some(handle) = self.hit(view, shape, coord) else {
+            return false;
+        };
+        let center = shape.get_pos();
+        let axis = self.axes(shape)[handle.axis];
+        let ray = view.ray(coord);
+        let (grab, grab_dir) = match handle.mode {
+            GizmoMode::Rotate => match ring_point(&ray, center, axis) {
+                Some(point) => (0.0, (point - center).normalize()),
+                None => return false,
+            },
+            GizmoMode::Translate | GizmoMode::Scale => match axis_param(&ray, center, axis) {
+                Some(t) => (t, axis),
+                None => return false,
+            },
+        };
+        let start_size = match shape.as_any().downcast_ref::<Cube>() {
+            Some(cube) => cube.bounds(),
+            None => shape
+                .as_any()
+                .downcast_ref::<Sphere>()
+                .map_or(Vector3::new(0.0, 0.0, 0.0), |sphere| {
+                    Vector3::new(1.0, 1.0, 1.0) * sphere.radius()
+                }),
+        };
+        self.drag = Some(Drag {
+            handle,
+            center,
+            axis,
+            grab,
+            grab_dir,
+            applied: 0.0,
+            start_size,
+        });
+        true
+    }
+
+    // Moves the dragged handle to `coord`, editing `shape`
+    pub fn drag(&mut self, view: &Viewport, shape: &mut dyn Shape, coord: (f32, f32)) {
+        let snapping = self.snapping;
+        let Some(drag) = &mut self.drag else {
+            return;
+        };
+        let ray = view.ray(coord);
+        match drag.handle.mode {
+            GizmoMode::Translate => {
+                let Some(t) = axis_param(&ray, drag.center, drag.axis) else {
+                    return;
+                };
+                let offset = snap(t - drag.grab, snapping.translate, snapping.enabled);
+                shape.translate(drag.axis * (offset - drag.applied));
+                drag.applied = offset;
+            }
+            GizmoMode::Rotate => {
+                let Some(point) = ring_point(&ray, drag.center, drag.axis) else {
+                    return;
+                };
+                let to = (point - drag.center).normalize();
+                let from = drag.grab_dir;
+                let angle = drag.axis.dot(from.cross(to)).atan2(from.dot(to));
+                let angle = snap(angle, snapping.rotate.0, snapping.enabled);
+                // Shape::rotate turns the shape's space, which turns the
+                // shape itself the other way
+                shape.rotate(Quaternion::from_axis_angle(
+                    drag.axis,
+                    Rad(drag.applied - angle),
+                ));
+                drag.applied = angle;
+            }
+            GizmoMode::Scale => {
+                let Some(t) = axis_param(&ray, drag.center, drag.axis) else {
+                    return;
+                };
+                let delta = t - drag.grab;
+                let axis = drag.handle.axis;
+                if let Some(cube) = shape.as_any_mut().downcast_mut::<Cube>() {
+                    let mut bounds = drag.start_size;
+                    bounds[axis] =
+                        snap(bounds[axis] + delta, snapping.scale, snapping.enabled).max(0.0);
+                    cube.set_bounds(bounds);
+                } else if let Some(sphere) = shape.as_any_mut().downcast_mut::<Sphere>() {
+                    let radius = snap(
+                        drag.start_size[axis] + delta,
+                        snapping.scale,
+                        snapping.enabled,
+                    );
+                    sphere.set_radius(radius.max(0.01));
+                }
+            }
+        }
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag = None;
+    }
+
+    // Draws the handles of `shape`, `active` highlighted. egui works in
+    // points, so pixels are divided by `pixels_per_point`.
+    pub fn paint(
+        &self,
+        painter: &egui::Painter,
+        view: &Viewport,
+        shape: &dyn Shape,
+        active: Option<Handle>,
+        pixels_per_point: f32,
+    ) {
+        let to_points = |p: egui::Pos2| (p.to_vec2() / pixels_per_point).to_pos2();
+        for (axis, axis_color) in AXIS_COLORS.into_iter().enumerate() {
+            let handle = Handle {
+                mode: self.mode,
+                axis,
+            };
+            let Some(outline) = self.handle_outline(view, shape, handle) else {
+                continue;
+            };
+            let color = match active == Some(handle) {
+                true => ACTIVE_COLOR,
+                false => axis_color,
+            };
+            let stroke = egui::Stroke::new(HANDLE_WIDTH, color);
+            let points: Vec<egui::Pos2> = outline.into_iter().map(to_points).collect();
+            let end = *points.last().unwrap();
+            match self.mode {
+                GizmoMode::Rotate => {
+                    painter.add(egui::Shape::line(points, stroke));
+                }
+                GizmoMode::Translate => {
+                    // arrow head
+                    let dir = (end - points[0]).normalized() * 3.0 * HANDLE_WIDTH;
+                    painter.line_segment([points[0], end], stroke);
+                    painter.add(egui::Shape::convex_polygon(
+                        vec![end + dir, end + dir.rot90() * 0.6, end - dir.rot90() * 0.6],
+                        color,
+                        egui::Stroke::NONE,
+                    ));
+                }
+                GizmoMode::Scale => {
+                    painter.line_segment([points[0], end], stroke);
+                    let size = egui::vec2(2.5, 2.5) * HANDLE_WIDTH;
+                    painter.rect_filled(egui::Rect::from_center_size(end, size), 0.0, color);
+                }
+            }
+        }
+    }
+
+    // The handle as a polyline in pixels, None if the shape doesn't have it
+    // or it is behind the camera
+    fn handle_outline(
+        &self,
+        view: &Viewport,
+        shape: &dyn Shape,
+        handle: Handle,
+    ) -> Option<Vec<egui::Pos2>> {
+        let any = shape.as_any();
+        let has_handle = match handle.mode {
+            GizmoMode::Translate | GizmoMode::Scale => any.is::<Cube>() || any.is::<Sphere>(),
+            // spheres look the same however they are turned
+            GizmoMode::Rotate => any.is::<Cube>(),
+        };
+        if !has_handle {
+            return None;
+        }
+        let center = shape.get_pos();
+        let length = GIZMO_SIZE * view.pixel_size(center);
+        let axes = self.axes(shape);
+        let axis = axes[handle.axis];
+        let points = match handle.mode {
+            GizmoMode::Translate | GizmoMode::Scale => vec![center, center + axis * length],
+            GizmoMode::Rotate => {
+                let (u, v) = (axes[(handle.axis + 1) % 3], axes[(handle.axis + 2) % 3]);
+                (0..=RING_SEGMENTS)
+                    .map(|i| {
+                        let angle = i as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+                        center + (u * angle.cos() + v * angle.sin()) * length
+                    })
+                    .collect()
+            }
+        };
+        points
+            .into_iter()
+            .map(|point| view.project(point).map(|(x, y)| egui::pos2(x, y)))
+            .collect()
+    }
+}
+
+fn snap(value: f32, increment: f32, enabled: bool) -> f32 {
+    match enabled && increment > 0.0 {
+        true => (value / increment).round() * increment,
+        false => value,
+    }
+}
+
+fn segment_distance(p: egui::Pos2, a: egui::Pos2, b: egui::Pos2) -> f32 {
+    let ab = b - a;
+    let t = ((p - a).dot(ab) / ab.length_sq().max(f32::EPSILON)).clamp(0.0, 1.0);
+    p.distance(a + ab * t)
+}
+
+// Distance along the line through `center` along `axis` of its closest point
+// to `ray`, None if they are parallel
+fn axis_param(ray: &Ray, center: Vector3<f32>, axis: Vector3<f32>) -> Option<f32> {
+    let w = ray.origin - center;
+    let b = ray.dir.dot(axis);
+    let denom = 1.0 - b * b;
+    if denom < 1e-6 {
+        return None;
+    }
+    Some((axis.dot(w) - b * ray.dir.dot(w)) / denom)
+}
+
+// Where `ray` crosses the plane through `center` normal to `axis`, None if
+// it is parallel to the plane or the point is too close to the center to
+// give a direction
+fn ring_point(ray: &Ray, center: Vector3<f32>, axis: Vector3<f32>) -> Option<Vector3<f32>> {
+    let denom = ray.dir.dot(axis);
+    if denom.abs() < 1e-6 {
+        return None;
+    }
+    let point = ray.at((center - ray.origin).dot(axis) / denom);
+    ((point - center).magnitude() > 1e-4).then_some(point)
+}
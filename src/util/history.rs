@@ -0,0 +1,258 @@
+use cgmath::{Quaternion, Vector3};
+
+use super::shapes::{Cube, Flag, Shape, ShapeManager, Sphere};
+
+// Undo steps kept before the oldest are dropped
+pub const MAX_STEPS: usize = 256;
+
+// The editable properties of a shape
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShapeState {
+    pub pos: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub color: Vector3<f32>,
+    pub enabled: bool,
+    // a cube's bounds, or a sphere's radius in every component
+    pub size: Vector3<f32>,
+}
+
+impl ShapeState {
+    pub fn of(shape: &dyn Shape) -> Self {
+        let any = shape.as_any();
+        let size = if let Some(cube) = any.downcast_ref::<Cube>() {
+            cube.bounds()
+        } else if let Some(sphere) = any.downcast_ref::<Sphere>() {
+            Vector3::new(1.0, 1.0, 1.0) * sphere.radius()
+        } else {
+            Vector3::new(0.0, 0.0, 0.0)
+        };
+        Self {
+            pos: shape.get_pos(),
+            rotation: shape.get_rotation(),
+            color: shape.get_color(),
+            enabled: shape.get_flag(Flag::Enabled),
+            size,
+        }
+    }
+
+    pub fn apply(&self, shape: &mut dyn Shape) {
+        shape.set_pos(self.pos);
+        shape.set_rotation(self.rotation);
+        shape.set_color(self.color);
+        shape.set_flag(Flag::Enabled, self.enabled);
+        let any = shape.as_any_mut();
+        if let Some(cube) = any.downcast_mut::<Cube>() {
+            cube.set_bounds(self.size);
+        } else if let Some(sphere) = any.downcast_mut::<Sphere>() {
+            sphere.set_radius(self.size.x);
+        }
+    }
+}
+
+// Changes to the shape list that go through the history. Anything holding
+// indices into the ShapeManager, such as a VideoWall, has to follow them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ListChange {
+    // `cube` is the shape's index among the cubes if it is one
+    Inserted { index: u32, cube: Option<u32> },
+    Removed { index: u32, cube: Option<u32> },
+}
+
+impl ListChange {
+    // Where the shape that was at `index` is after the change, None if it
+    // was removed
+    pub fn shift(&self, index: u32) -> Option<u32> {
+        match *self {
+            ListChange::Inserted { index: at, .. } if at <= index => Some(index + 1),
+            ListChange::Removed { index: at, .. } if at == index => None,
+            ListChange::Removed { index: at, .. } if at < index => Some(index - 1),
+            _ => Some(index),
+        }
+    }
+}
+
+// Edits to the shape list
+pub enum Command {
+    AddSphere {
+        pos: Vector3<f32>,
+        radius: f32,
+        color: Vector3<f32>,
+    },
+    AddCube {
+        pos: Vector3<f32>,
+        bounds: Vector3<f32>,
+        color: Vector3<f32>,
+    },
+    Remove(u32),
+}
+
+// One recorded edit, kept as what undoes it. Applying an edit gives the edit
+// that undoes that in turn.
+enum Edit {
+    Insert { index: u32, shape: Box<dyn Shape> },
+    Remove { index: u32 },
+    Set { index: u32, state: ShapeState },
+}
+
+impl Edit {
+    fn apply(self, shapes: &mut ShapeManager) -> Option<(Edit, Option<ListChange>)> {
+        match self {
+            Edit::Insert { index, shape } => {
+                shapes.insert_shape(index, shape);
+                let cube = cube_index(shapes.get_shape(index)?);
+                Some((Edit::Remove { index }, Some(ListChange::Inserted { index, cube })))
+            }
+            Edit::Remove { index } => {
+                let shape = shapes.remove_shape(index)?;
+                let cube = cube_index(shape.as_ref());
+                Some((Edit::Insert { index, shape }, Some(ListChange::Removed { index, cube })))
+            }
+            Edit::Set { index, state } => {
+                let shape = shapes.get_shape_mut(index)?;
+                let undo = ShapeState::of(shape.as_ref());
+                state.apply(shape.as_mut());
+                Some((Edit::Set { index, state: undo }, None))
+            }
+        }
+    }
+}
+
+fn cube_index(shape: &dyn Shape) -> Option<u32> {
+    shape.as_any().is::<Cube>().then(|| shape.get_index())
+}
+
+// Undo and redo stacks for edits to a ShapeManager. Edits made while a group
+// is open, such as the frames of a drag, are undone together.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Vec<Edit>>,
+    redo: Vec<Vec<Edit>>,
+    group: Option<Vec<Edit>>,
+}
+
+impl History {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.group.as_ref().is_some_and(|group| !group.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn begin_group(&mut self) {
+        if self.group.is_none() {
+            self.group = Some(vec![]);
+        }
+    }
+
+    pub fn end_group(&mut self) {
+        if let Some(group) = self.group.take() {
+            self.push_step(group);
+        }
+    }
+
+    pub fn execute(&mut self, shapes: &mut ShapeManager, command: Command) -> Option<ListChange> {
+        let (undo, change) = match command {
+            Command::AddSphere { pos, radius, color } => {
+                shapes.new_sphere(pos, radius, color);
+                let index = shapes.shape_count() - 1;
+                (Edit::Remove { index }, ListChange::Inserted { index, cube: None })
+            }
+            Command::AddCube { pos, bounds, color } => {
+                let cube = shapes.new_cube(pos, bounds, color).get_index();
+                let index = shapes.shape_count() - 1;
+                let change = ListChange::Inserted {
+                    index,
+                    cube: Some(cube),
+                };
+                (Edit::Remove { index }, change)
+            }
+            Command::Remove(index) => {
+                let (undo, change) = Edit::Remove { index }.apply(shapes)?;
+                (undo, change?)
+            }
+        };
+        self.record(undo);
+        Some(change)
+    }
+
+    // Runs `edit` on the shape at `index` and records what it changed.
+    // Returns what `edit` returned, None if there is no such shape.
+    pub fn edit<R>(
+        &mut self,
+        shapes: &mut ShapeManager,
+        index: u32,
+        edit: impl FnOnce(&mut dyn Shape) -> R,
+    ) -> Option<R> {
+        let shape = shapes.get_shape_mut(index)?;
+        let before = ShapeState::of(shape.as_ref());
+        let result = edit(shape.as_mut());
+        if ShapeState::of(shape.as_ref()) != before {
+            self.record(Edit::Set {
+                index,
+                state: before,
+            });
+        }
+        Some(result)
+    }
+
+    // Undoes the last step, returning the changes it made to the shape list
+    // in order. None if there was nothing to undo.
+    pub fn undo(&mut self, shapes: &mut ShapeManager) -> Option<Vec<ListChange>> {
+        self.end_group();
+        let step = self.undo.pop()?;
+        let (redo, changes) = Self::apply(step, shapes);
+        self.redo.push(redo);
+        Some(changes)
+    }
+
+    pub fn redo(&mut self, shapes: &mut ShapeManager) -> Option<Vec<ListChange>> {
+        self.end_group();
+        let step = self.redo.pop()?;
+        let (undo, changes) = Self::apply(step, shapes);
+        self.undo.push(undo);
+        Some(changes)
+    }
+
+    // Applies the edits of `step` last to first, returning the step that
+    // reverses it
+    fn apply(step: Vec<Edit>, shapes: &mut ShapeManager) -> (Vec<Edit>, Vec<ListChange>) {
+        let mut reverse = vec![];
+        let mut changes = vec![];
+        for edit in step.into_iter().rev() {
+            if let Some((edit, change)) = edit.apply(shapes) {
+                reverse.push(edit);
+                changes.extend(change);
+            }
+        }
+        reverse.reverse();
+        (reverse, changes)
+    }
+
+    fn record(&mut self, undo: Edit) {
+        self.redo.clear();
+        match &mut self.group {
+            Some(group) => {
+                // a drag only needs the state it started from
+                let repeated = matches!(
+                    (group.last(), &undo),
+                    (Some(Edit::Set { index: a, .. }), Edit::Set { index: b, .. }) if a == b
+                );
+                if !repeated {
+                    group.push(undo);
+                }
+            }
+            None => self.push_step(vec![undo]),
+        }
+    }
+
+    fn push_step(&mut self, step: Vec<Edit>) {
+        if step.is_empty() {
+            return;
+        }
+        self.undo.push(step);
+        if self.undo.len() > MAX_STEPS {
+            self.undo.remove(0);
+        }
+    }
+}
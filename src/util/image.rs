@@ -1,61 +1,372 @@
 extern crate image;
 
-use image::{GenericImageView, Pixel};
+use std::fs;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
 use image::open;
 use image::imageops;
+use image::{AnimationDecoder, ColorType, DynamicImage, ImageFormat};
+
+use super::frame_stream::{FrameStream, DEFAULT_DECODE_AHEAD};
+
+// Channel layout of a frame's pixels, one byte per channel
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    L8,
+    La8,
+    Rgb8,
+    Rgba8,
+}
+
+impl PixelFormat {
+    pub fn channels(self) -> usize {
+        match self {
+            PixelFormat::L8 => 1,
+            PixelFormat::La8 => 2,
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Rgba8 => 4,
+        }
+    }
+
+    pub fn has_alpha(self) -> bool {
+        matches!(self, PixelFormat::La8 | PixelFormat::Rgba8)
+    }
+
+    pub fn is_gray(self) -> bool {
+        matches!(self, PixelFormat::L8 | PixelFormat::La8)
+    }
+}
+
+// One decoded frame. Pixels are stored row major in `format`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    format: PixelFormat,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(format: PixelFormat, width: u32, height: u32, data: Vec<u8>) -> Self {
+        assert_eq!(
+            data.len(),
+            width as usize * height as usize * format.channels(),
+            "frame data does not match its size and format"
+        );
+        Self {
+            format,
+            width,
+            height,
+            data,
+        }
+    }
+
+    // Magenta and black checkerboard shown in place of missing frames
+    pub fn placeholder(width: u32, height: u32) -> Self {
+        let mut data = Vec::with_capacity(width as usize * height as usize * 3);
+        for y in 0..height {
+            for x in 0..width {
+                let magenta = (x + y) % 2 == 0;
+                data.extend_from_slice(if magenta { &[255, 0, 255] } else { &[0, 0, 0] });
+            }
+        }
+        Self::new(PixelFormat::Rgb8, width, height, data)
+    }
+
+    // Keeps 8 bit images as they are, anything deeper is converted to 8 bits
+    // with or without alpha.
+    pub fn from_image(img: DynamicImage) -> Self {
+        let (width, height) = (img.width(), img.height());
+        let (format, data) = match img {
+            DynamicImage::ImageLuma8(img) => (PixelFormat::L8, img.into_raw()),
+            DynamicImage::ImageLumaA8(img) => (PixelFormat::La8, img.into_raw()),
+            DynamicImage::ImageRgb8(img) => (PixelFormat::Rgb8, img.into_raw()),
+            DynamicImage::ImageRgba8(img) => (PixelFormat::Rgba8, img.into_raw()),
+            img => match img.color() {
+                ColorType::L16 => (PixelFormat::L8, img.into_luma8().into_raw()),
+                ColorType::La16 => (PixelFormat::La8, img.into_luma_alpha8().into_raw()),
+                c if c.has_alpha() => (PixelFormat::Rgba8, img.into_rgba8().into_raw()),
+                _ => (PixelFormat::Rgb8, img.into_rgb8().into_raw()),
+            },
+        };
+        Self::new(format, width, height, data)
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn channels(&self, x: u32, y: u32) -> &[u8] {
+        let n = self.format.channels();
+        let i = (y * self.width + x) as usize * n;
+        &self.data[i..i + n]
+    }
+
+    // The pixel as RGBA, gray is spread over r, g and b and missing alpha is opaque
+    pub fn rgba(&self, x: u32, y: u32) -> [u8; 4] {
+        match *self.channels(x, y) {
+            [l] => [l, l, l, 255],
+            [l, a] => [l, l, l, a],
+            [r, g, b] => [r, g, b, 255],
+            [r, g, b, a] => [r, g, b, a],
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn color(&self, x: u32, y: u32) -> [f32; 3] {
+        let [r, g, b, _] = self.rgba(x, y);
+        [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0]
+    }
+
+    pub fn alpha(&self, x: u32, y: u32) -> f32 {
+        self.rgba(x, y)[3] as f32 / 255.0
+    }
+
+    // Rec. 709 luma of the pixel in [0, 1], ignoring alpha
+    pub fn luminance(&self, x: u32, y: u32) -> f32 {
+        if self.format.is_gray() {
+            return self.channels(x, y)[0] as f32 / 255.0;
+        }
+        let [r, g, b] = self.color(x, y);
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    // Luminance of every pixel, row major
+    pub fn luminance_view(&self) -> Vec<f32> {
+        let mut view = Vec::with_capacity((self.width * self.height) as usize);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                view.push(self.luminance(x, y));
+            }
+        }
+        view
+    }
+}
+
+enum Source {
+    Memory(Vec<Arc<Frame>>),
+    Stream(FrameStream),
+}
+
+// Shortest frame delay honoured in animated files. Browsers show GIFs with
+// smaller delays (often 0) at 10 fps, so we do too.
+const MIN_FRAME_DELAY: f32 = 0.02;
+const DEFAULT_FRAME_DELAY: f32 = 0.1;
 
 pub struct Video {
     width: u32,
     height: u32,
-    frames: Vec<Vec<f32>>,
+    source: Source,
+    // Start time of each frame plus the end of the last one, in seconds. Only
+    // animated files have their own timing, sequences play at a given fps.
+    frame_starts: Option<Vec<f32>>,
 }
 
 impl Video {
+    // Streams an image sequence from `path`, decoding frames on a background
+    // thread as playback reaches them. See `sequence_paths` for what `path`
+    // can be.
     pub fn new(path: &str, width: u32, height: u32) -> Self {
-        let mut index = 1; // ffmpeg starts at 1
+        Self::stream(path, width, height, DEFAULT_DECODE_AHEAD)
+    }
+
+    // Streams with at most `decode_ahead` frames in memory.
+    pub fn stream(path: &str, width: u32, height: u32, decode_ahead: usize) -> Self {
+        let paths = sequence_paths(path);
+        Self {
+            width,
+            height,
+            source: Source::Stream(FrameStream::new(paths, width, height, decode_ahead)),
+            frame_starts: None,
+        }
+    }
+
+    // Decodes the whole sequence up front.
+    pub fn load(path: &str, width: u32, height: u32) -> Self {
         let mut frames = vec![];
-        loop {
-            let path = format!("{}/f{:0>4}.png", path, index);
+        for path in sequence_paths(path) {
             let img = match open(path) {
                 Ok(img) => img,
                 Err(_) => break,
             };
-            let img = img.resize(width, height, imageops::FilterType::Nearest);
-            let mut frame = vec![];
-            for pixel in img.pixels() {
-                let mut i = pixel.2.channels().iter().map(|a| *a as f32);
-                let (r, g, b) = (i.next().unwrap(), i.next().unwrap(), i.next().unwrap());
-                let p = (r + g + b) as f32 / (255.0 * 3.0);
-                frame.push(p);
+            let img = img.resize_exact(width, height, imageops::FilterType::Nearest);
+            frames.push(Frame::from_image(img));
+        }
+        Self::from_frames(width, height, frames)
+    }
+
+    // Opens any supported source: an animated GIF or PNG (decoded up front,
+    // with the file's own frame delays), a still image (one frame) or else a
+    // streamed sequence.
+    pub fn open(path: &str, width: u32, height: u32) -> io::Result<Self> {
+        let file = Path::new(path);
+        if !file.is_file() {
+            let video = Self::new(path, width, height);
+            if video.get_frame_count() == 0 {
+                let message = format!("no frames found at {}", path);
+                return Err(io::Error::new(io::ErrorKind::NotFound, message));
             }
-            frames.push(frame);
-            index += 1;
+            return Ok(video);
+        }
+
+        let reader = BufReader::new(fs::File::open(file)?);
+        let frames = match ImageFormat::from_path(file).map_err(invalid_data)? {
+            ImageFormat::Gif => Some(GifDecoder::new(reader).map_err(invalid_data)?.into_frames()),
+            ImageFormat::Png => {
+                let decoder = PngDecoder::new(reader).map_err(invalid_data)?;
+                decoder.is_apng().then(|| decoder.apng().into_frames())
+            }
+            _ => None,
+        };
+        let frames = match frames {
+            Some(frames) => frames.collect_frames().map_err(invalid_data)?,
+            None => {
+                let img = open(file).map_err(invalid_data)?;
+                let img = img.resize_exact(width, height, imageops::FilterType::Nearest);
+                return Ok(Self::from_frames(width, height, vec![Frame::from_image(img)]));
+            }
+        };
+
+        let mut delays = vec![];
+        let frames = frames
+            .into_iter()
+            .map(|frame| {
+                let (numer, denom) = frame.delay().numer_denom_ms();
+                let delay = numer as f32 / denom as f32 / 1000.0;
+                delays.push(if delay < MIN_FRAME_DELAY {
+                    DEFAULT_FRAME_DELAY
+                } else {
+                    delay
+                });
+                let img = imageops::resize(
+                    &frame.into_buffer(),
+                    width,
+                    height,
+                    imageops::FilterType::Nearest,
+                );
+                Frame::from_image(DynamicImage::ImageRgba8(img))
+            })
+            .collect();
+        Ok(Self::from_frames(width, height, frames).with_delays(&delays))
+    }
+
+    // Every frame must be `width` by `height`, formats may differ.
+    pub fn from_frames(width: u32, height: u32, frames: Vec<Frame>) -> Self {
+        for frame in &frames {
+            assert_eq!(
+                (frame.width(), frame.height()),
+                (width, height),
+                "video frames must all be the same size"
+            );
         }
         Self {
             width,
             height,
-            frames,
+            source: Source::Memory(frames.into_iter().map(Arc::new).collect()),
+            frame_starts: None,
+        }
+    }
+
+    // Gives each frame its own duration in seconds instead of a fixed fps.
+    pub fn with_delays(mut self, delays: &[f32]) -> Self {
+        assert_eq!(delays.len(), self.get_frame_count(), "one delay per frame");
+        let mut starts = vec![0.0];
+        for delay in delays {
+            starts.push(starts[starts.len() - 1] + delay);
+        }
+        self.frame_starts = Some(starts);
+        self
+    }
+
+    // Seconds each frame is shown for, if the video has its own timing
+    pub fn delays(&self) -> Option<Vec<f32>> {
+        let starts = self.frame_starts.as_ref()?;
+        Some(starts.windows(2).map(|w| w[1] - w[0]).collect())
+    }
+
+    // Length in seconds, `fps` is only used without per-frame delays.
+    pub fn duration(&self, fps: f32) -> f32 {
+        match &self.frame_starts {
+            Some(starts) => starts[starts.len() - 1],
+            None => self.get_frame_count() as f32 / fps,
         }
     }
 
-    pub fn get_frame(&self, index: usize) -> &[f32] {
-        &self.frames[index]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn is_streaming(&self) -> bool {
+        matches!(self.source, Source::Stream(_))
+    }
+
+    // Returns the frame if it is ready, never waiting on a decode. Streaming
+    // videos start decoding from `index` onwards.
+    pub fn get_frame(&self, index: usize) -> Option<Arc<Frame>> {
+        match &self.source {
+            Source::Memory(frames) => frames.get(index).cloned(),
+            Source::Stream(stream) => stream.frame(index),
+        }
+    }
+
+    // Returns the frame, waiting for it to be decoded if needed.
+    pub fn wait_frame(&self, index: usize) -> Arc<Frame> {
+        match &self.source {
+            Source::Memory(frames) => frames[index].clone(),
+            Source::Stream(stream) => stream.wait_frame(index),
+        }
     }
 
     pub fn get_frame_count(&self) -> usize {
-        self.frames.len()
+        match &self.source {
+            Source::Memory(frames) => frames.len(),
+            Source::Stream(stream) => stream.frame_count(),
+        }
     }
 
+    // The get_pixel_* and formatted frame helpers wait for the frame, use
+    // get_frame on the render thread.
+
+    // Luminance of pixel (x, y) in [0, 1]
     pub fn get_pixel_value(&self, index: usize, x: u32, y: u32) -> f32 {
-        self.frames[index][(y * self.width + x) as usize]
+        self.wait_frame(index).luminance(x, y)
+    }
+
+    pub fn get_pixel_color(&self, index: usize, x: u32, y: u32) -> [f32; 3] {
+        self.wait_frame(index).color(x, y)
+    }
+
+    pub fn get_pixel_alpha(&self, index: usize, x: u32, y: u32) -> f32 {
+        self.wait_frame(index).alpha(x, y)
     }
 
     pub fn get_rc_formatted_frame(&self, index: usize) -> Vec<Vec<f32>> {
+        let pixels = self.wait_frame(index);
         let mut frame = vec![];
         for y in 0..self.height {
             let mut row = vec![];
             for x in 0..self.width {
-                row.push(self.get_pixel_value(index, x, y));
+                row.push(pixels.luminance(x, y));
             }
             frame.push(row);
         }
@@ -63,29 +374,109 @@ impl Video {
     }
 
     pub fn get_rc_formatted_frame_string(&self, index: usize) -> String {
+        let pixels = self.wait_frame(index);
         let mut frame = String::new();
         for y in 0..self.height {
             for x in 0..self.width {
-                frame.push_str(&format!("{:.4}, ", self.get_pixel_value(index, x, y)));
+                frame.push_str(&format!("{:.4}, ", pixels.luminance(x, y)));
             }
-            frame.push_str(&format!("\n"));
+            frame.push('\n');
         }
         frame
     }
 
     pub fn get_xy_formatted_frame(&self, index: usize) -> Vec<Vec<f32>> {
+        let pixels = self.wait_frame(index);
         let mut frame = vec![];
         for x in 0..self.width {
             let mut row = vec![];
             for y in 0..self.height {
-                row.push(self.get_pixel_value(index, x, y));
+                row.push(pixels.luminance(x, y));
             }
             frame.push(row);
         }
         frame
     }
 
+    // `fps` is only used without per-frame delays.
     pub fn frame_index_from_time(&self, time: f32, fps: f32) -> usize {
-        (time * fps) as usize
+        match &self.frame_starts {
+            Some(starts) => starts.partition_point(|start| *start <= time).saturating_sub(1),
+            None => (time * fps) as usize,
+        }
     }
-}
\ No newline at end of file
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+// Frames of an image sequence, ordered by the last number in their file
+// names so `frame9.png` comes before `frame10.png`. `path` is either a
+// directory, whose images are all used, or a file name pattern where `*`
+// matches any run of characters and `?` any one character, like
+// `./assets/apple/f*.png`.
+pub fn sequence_paths(path: &str) -> Vec<PathBuf> {
+    let path = Path::new(path);
+    let (dir, pattern) = if path.is_dir() {
+        (path, "*")
+    } else {
+        let dir = path.parent().filter(|p| !p.as_os_str().is_empty());
+        let pattern = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        (dir.unwrap_or(Path::new(".")), pattern)
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && ImageFormat::from_path(p).is_ok())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|name| matches_pattern(pattern, name))
+        })
+        .collect();
+    paths.sort_by_cached_key(|p| (frame_number(p), p.clone()));
+    paths
+}
+
+// The last run of digits in the file stem
+fn frame_number(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?;
+    let end = stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = stem[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    stem[start..end].parse().ok()
+}
+
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    // position after the last `*` in both strings, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    star = Some((sp, sn + 1));
+                    p = sp;
+                    n = sn + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
@@ -0,0 +1,321 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use winit::event::{MouseButton, VirtualKeyCode};
+
+// Everything the user can trigger from the keyboard or mouse. Camera movement
+// actions are held, the rest fire once per press.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    // Camera movement
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    ZoomIn,
+    ZoomOut,
+    RollLeft,
+    RollRight,
+    Look, // rotate the camera while held
+    Pan,  // pan the orbit camera while held
+
+    // Camera toggles
+    ToggleProjection,
+    ToggleCameraMode,
+    ToggleFreeCamera,
+    FrameScene,
+
+    // Camera path
+    CaptureKeyframe,
+    ClearPath,
+    PlayPath,
+
+    // Video wall
+    PlayVideo,
+    StopVideo,
+    SeekVideoBackward,
+    SeekVideoForward,
+    CycleVideoPlayback, // stop, loop, ping-pong or hold the last frame
+    CycleVideoMapping,
+
+    // Scene clock
+    TogglePause,
+    StepFrame, // advance one frame while paused
+    SlowDown,
+    SpeedUp,
+    SeekBackward,
+    SeekForward,
+
+    // Recording
+    ToggleRecording,
+    CycleRecordFormat,
+    Screenshot,
+
+    // Editing
+    Select, // click a shape to select it, or empty space to clear
+    CycleGizmoMode, // translate, rotate or scale the selected shape
+    ToggleGizmoSpace, // world or local axes
+    ToggleSnapping,
+    Undo,
+    Redo,
+
+    // App
+    ToggleInspector,
+    Rebind,
+    Quit,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    // a key pressed while holding Ctrl, and Shift if `shift`
+    Ctrl { key: VirtualKeyCode, shift: bool },
+}
+
+// Maps bindings to actions. Several bindings can trigger the same action.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Action::*;
+        use Binding::*;
+        use VirtualKeyCode as K;
+
+        let bindings = [
+            (MoveForward, vec![Key(K::W), Key(K::Up)]),
+            (MoveBackward, vec![Key(K::S), Key(K::Down)]),
+            (MoveLeft, vec![Key(K::A), Key(K::Left)]),
+            (MoveRight, vec![Key(K::D), Key(K::Right)]),
+            (MoveUp, vec![Key(K::Space)]),
+            (MoveDown, vec![Key(K::LShift)]),
+            (ZoomIn, vec![Key(K::Equals), Key(K::NumpadAdd)]),
+            (ZoomOut, vec![Key(K::Minus), Key(K::NumpadSubtract)]),
+            (RollLeft, vec![Key(K::Z)]),
+            (RollRight, vec![Key(K::X)]),
+            (Look, vec![Mouse(MouseButton::Left)]),
+            (Pan, vec![Mouse(MouseButton::Middle)]),
+            (ToggleProjection, vec![Key(K::O)]),
+            (ToggleCameraMode, vec![Key(K::Tab)]),
+            (ToggleFreeCamera, vec![Key(K::R)]),
+            (FrameScene, vec![Key(K::F)]),
+            (CaptureKeyframe, vec![Key(K::K)]),
+            (ClearPath, vec![Key(K::J)]),
+            (PlayPath, vec![Key(K::P)]),
+            (PlayVideo, vec![Key(K::Q)]),
+            (StopVideo, vec![Key(K::Back)]),
+            (SeekVideoBackward, vec![Key(K::LBracket)]),
+            (SeekVideoForward, vec![Key(K::RBracket)]),
+            (CycleVideoPlayback, vec![Key(K::L)]),
+            (CycleVideoMapping, vec![Key(K::M)]),
+            (TogglePause, vec![Key(K::F5)]),
+            (StepFrame, vec![Key(K::F6)]),
+            (SlowDown, vec![Key(K::F7)]),
+            (SpeedUp, vec![Key(K::F8)]),
+            (SeekBackward, vec![Key(K::Comma)]),
+            (SeekForward, vec![Key(K::Period)]),
+            (ToggleRecording, vec![Key(K::F9)]),
+            (CycleRecordFormat, vec![Key(K::F10)]),
+            (Screenshot, vec![Key(K::F12)]),
+            (Select, vec![Mouse(MouseButton::Left)]),
+            (CycleGizmoMode, vec![Key(K::G)]),
+            (ToggleGizmoSpace, vec![Key(K::B)]),
+            (ToggleSnapping, vec![Key(K::N)]),
+            (Undo, vec![Ctrl { key: K::Z, shift: false }]),
+            (Redo, vec![Ctrl { key: K::Z, shift: true }]),
+            (ToggleInspector, vec![Key(K::F1)]),
+            (Rebind, vec![Key(K::F2)]),
+            (Quit, vec![Key(K::Escape)]),
+        ];
+        Self {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+}
+
+impl InputMap {
+    // Actions missing from the file keep their default bindings, minus any
+    // the file gives to other actions, as if each had been rebound.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let loaded: InputMap =
+            ron::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut map = InputMap::default();
+        for (action, bindings) in &mut map.bindings {
+            if !loaded.bindings.contains_key(action) {
+                bindings.retain(|b| loaded.actions(*b).next().is_none());
+            }
+        }
+        map.bindings.extend(loaded.bindings);
+        Ok(map)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+
+    pub fn actions(&self, binding: Binding) -> impl Iterator<Item = Action> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, b)| b.contains(&binding))
+            .map(|(a, _)| *a)
+    }
+
+    pub fn is_bound(&self, binding: Binding, action: Action) -> bool {
+        self.bindings(action).contains(&binding)
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], |b| b.as_slice())
+    }
+
+    pub fn add_binding(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    // Makes `binding` the only binding of `action`, taking it away from any
+    // other action so one press never triggers two things by accident.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        for bindings in self.bindings.values_mut() {
+            bindings.retain(|b| *b != binding);
+        }
+        self.bindings.insert(action, vec![binding]);
+    }
+
+    pub fn unbind(&mut self, action: Action) {
+        self.bindings.insert(action, vec![]);
+    }
+}
+
+// Runtime rebinding: press the Rebind key, then a binding of the action to
+// change, then its new binding. When that binding triggers several actions,
+// a number key picks which one to change. A modifier key only becomes the
+// new binding if it is released alone, so holding Ctrl and pressing a key
+// binds the chord.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rebinding {
+    Idle,
+    SelectAction,
+    ChooseAction(Binding),
+    NewBinding(Action),
+    // a modifier of the new binding is held down
+    ModifierHeld(Action, VirtualKeyCode),
+}
+
+impl Rebinding {
+    // Feeds a pressed binding through the rebinding steps. Returns true if the
+    // press was consumed.
+    pub fn process(&mut self, binding: Binding, input_map: &mut InputMap) -> bool {
+        match *self {
+            Rebinding::Idle => false,
+            Rebinding::SelectAction => {
+                let actions: Vec<Action> = input_map.actions(binding).collect();
+                match actions.as_slice() {
+                    [] => {
+                        println!("Nothing is bound to {:?}, rebinding cancelled", binding);
+                        *self = Rebinding::Idle;
+                    }
+                    [action] => {
+                        println!("Rebinding {:?}, press the new key or button", action);
+                        *self = Rebinding::NewBinding(*action);
+                    }
+                    _ => {
+                        println!("{:?} triggers several actions, press the number of one:", binding);
+                        for (i, action) in actions.iter().enumerate() {
+                            println!("    {}: {:?}", i + 1, action);
+                        }
+                        *self = Rebinding::ChooseAction(binding);
+                    }
+                }
+                true
+            }
+            Rebinding::ChooseAction(shared) => {
+                let chosen = number_key(binding)
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|i| input_map.actions(shared).nth(i));
+                match chosen {
+                    Some(action) => {
+                        println!("Rebinding {:?}, press the new key or button", action);
+                        *self = Rebinding::NewBinding(action);
+                    }
+                    None => {
+                        println!("No action picked, rebinding cancelled");
+                        *self = Rebinding::Idle;
+                    }
+                }
+                true
+            }
+            Rebinding::NewBinding(action) | Rebinding::ModifierHeld(action, _) => {
+                match modifier_key(binding) {
+                    Some(key) => *self = Rebinding::ModifierHeld(action, key),
+                    None => self.bind(action, binding, input_map),
+                }
+                true
+            }
+        }
+    }
+
+    // Feeds a released binding through the rebinding steps. Returns true if
+    // the release was consumed.
+    pub fn release(&mut self, binding: Binding, input_map: &mut InputMap) -> bool {
+        match *self {
+            Rebinding::ModifierHeld(action, key) if binding == Binding::Key(key) => {
+                self.bind(action, binding, input_map);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn bind(&mut self, action: Action, binding: Binding, input_map: &mut InputMap) {
+        input_map.rebind(action, binding);
+        println!("Bound {:?} to {:?}", action, binding);
+        *self = Rebinding::Idle;
+    }
+}
+
+// The key of a binding that is just a modifier, pressed with or without Ctrl
+fn modifier_key(binding: Binding) -> Option<VirtualKeyCode> {
+    use VirtualKeyCode as K;
+    let (Binding::Key(key) | Binding::Ctrl { key, .. }) = binding else {
+        return None;
+    };
+    matches!(
+        key,
+        K::LControl | K::RControl | K::LShift | K::RShift | K::LAlt | K::RAlt | K::LWin | K::RWin
+    )
+    .then_some(key)
+}
+
+// The digit on a number row or numpad key
+fn number_key(binding: Binding) -> Option<usize> {
+    use VirtualKeyCode as K;
+    let Binding::Key(key) = binding else {
+        return None;
+    };
+    let digits = [
+        [K::Key0, K::Numpad0],
+        [K::Key1, K::Numpad1],
+        [K::Key2, K::Numpad2],
+        [K::Key3, K::Numpad3],
+        [K::Key4, K::Numpad4],
+        [K::Key5, K::Numpad5],
+        [K::Key6, K::Numpad6],
+        [K::Key7, K::Numpad7],
+        [K::Key8, K::Numpad8],
+        [K::Key9, K::Numpad9],
+    ];
+    digits.iter().position(|keys| keys.contains(&key))
+}
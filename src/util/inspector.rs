@@ -0,0 +1,428 @@
+use cgmath::{Deg, EuclideanSpace, Euler, Point3, Quaternion, Vector3};
+
+use super::camera::{Camera, CameraController, CameraMode, Projection, ProjectionMode};
+use super::gizmo::{Gizmo, GizmoMode, GizmoSpace};
+use super::history::History;
+use super::shapes::{Cube, Flag, Shape, ShapeManager, Sphere};
+use crate::{Quality, ShaderParams};
+
+// Changes the inspector can't make by itself
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InspectorRequest {
+    AddSphere,
+    AddCube,
+    Remove(u32),
+    Undo,
+    Redo,
+    // the camera was edited, the controller needs to catch up
+    SyncCamera,
+}
+
+// Everything the inspector shows and edits
+pub struct InspectorTarget<'a> {
+    pub shapes: &'a mut ShapeManager,
+    pub camera: &'a mut Camera,
+    pub projection: &'a mut Projection,
+    pub camera_controller: &'a mut CameraController,
+    pub params: &'a mut ShaderParams,
+    // index in `shapes` of the shape being edited
+    pub selected: &'a mut Option<u32>,
+    pub gizmo: &'a mut Gizmo,
+    // shape edits go through here so they can be undone
+    pub history: &'a mut History,
+    // shapes that can't be removed, such as the video wall's cubes
+    pub locked: &'a [u32],
+}
+
+// An egui window listing the scene's shapes with editors for the selected
+// one, the camera and the march settings.
+#[derive(Default)]
+pub struct Inspector {
+    visible: bool,
+}
+
+impl Inspector {
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, target: InspectorTarget) -> Vec<InspectorRequest> {
+        let mut requests = vec![];
+        // edits made while a drag value or slider is held are undone together
+        if ctx.input().pointer.any_down() {
+            target.history.begin_group();
+        } else {
+            target.history.end_group();
+        }
+        egui::Window::new("Inspector")
+            .default_pos((10.0, 10.0))
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                egui::CollapsingHeader::new("Shapes")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        shapes_ui(
+                            ui,
+                            target.shapes,
+                            target.selected,
+                            target.history,
+                            target.locked,
+                            &mut requests,
+                        )
+                    });
+                egui::CollapsingHeader::new("Gizmo").show(ui, |ui| gizmo_ui(ui, target.gizmo));
+                egui::CollapsingHeader::new("Camera").show(ui, |ui| {
+                    if camera_ui(ui, target.camera, target.projection, target.camera_controller) {
+                        requests.push(InspectorRequest::SyncCamera);
+                    }
+                });
+                egui::CollapsingHeader::new("Rendering").show(ui, |ui| {
+                    params_ui(ui, target.params, target.shapes)
+                });
+            });
+        requests
+    }
+}
+
+fn shapes_ui(
+    ui: &mut egui::Ui,
+    shapes: &mut ShapeManager,
+    selected: &mut Option<u32>,
+    history: &mut History,
+    locked: &[u32],
+    requests: &mut Vec<InspectorRequest>,
+) {
+    if selected.is_some_and(|i| i >= shapes.shape_count()) {
+        *selected = None;
+    }
+
+    ui.horizontal(|ui| {
+        if ui.button("Add sphere").clicked() {
+            requests.push(InspectorRequest::AddSphere);
+        }
+        if ui.button("Add cube").clicked() {
+            requests.push(InspectorRequest::AddCube);
+        }
+        let removable = selected.filter(|i| !locked.contains(i));
+        let remove = ui.add_enabled(removable.is_some(), egui::Button::new("Remove"));
+        if let (true, Some(i)) = (remove.clicked(), removable) {
+            requests.push(InspectorRequest::Remove(i));
+        }
+    });
+    ui.horizontal(|ui| {
+        if ui.add_enabled(history.can_undo(), egui::Button::new("Undo")).clicked() {
+            requests.push(InspectorRequest::Undo);
+        }
+        if ui.add_enabled(history.can_redo(), egui::Button::new("Redo")).clicked() {
+            requests.push(InspectorRequest::Redo);
+        }
+    });
+
+    let row_height = ui.text_style_height(&egui::TextStyle::Body);
+    egui::ScrollArea::vertical()
+        .max_height(200.0)
+        .auto_shrink([false, true])
+        .show_rows(ui, row_height, shapes.shape_count() as usize, |ui, rows| {
+            for i in rows {
+                let i = i as u32;
+                let mut label = shapes.get_shape(i).map_or(String::new(), shape_name);
+                if locked.contains(&i) {
+                    label += " (video wall)";
+                }
+                if ui
+                    .selectable_label(*selected == Some(i), format!("{}: {}", i, label))
+                    .clicked()
+                {
+                    *selected = Some(i);
+                }
+            }
+        });
+
+    if let Some(i) = *selected {
+        ui.separator();
+        history.edit(shapes, i, |shape| shape_ui(ui, shape));
+    }
+}
+
+fn shape_name(shape: &dyn Shape) -> String {
+    if shape.as_any().is::<Sphere>() {
+        format!("Sphere {}", shape.get_index())
+    } else if shape.as_any().is::<Cube>() {
+        format!("Cube {}", shape.get_index())
+    } else {
+        format!("Union {}", shape.get_index())
+    }
+}
+
+// Drag values for the three components of `v`, true if any changed
+fn vector_ui(ui: &mut egui::Ui, label: &str, v: &mut Vector3<f32>, speed: f32) -> bool {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let mut changed = false;
+        for value in AsMut::<[f32; 3]>::as_mut(v) {
+            changed |= ui.add(egui::DragValue::new(value).speed(speed)).changed();
+        }
+        changed
+    })
+    .inner
+}
+
+fn shape_ui(ui: &mut egui::Ui, shape: &mut dyn Shape) {
+    let mut enabled = shape.get_flag(Flag::Enabled);
+    if ui.checkbox(&mut enabled, "Enabled").changed() {
+        shape.set_flag(Flag::Enabled, enabled);
+    }
+
+    let mut pos = shape.get_pos();
+    if vector_ui(ui, "Position", &mut pos, 0.05) {
+        shape.set_pos(pos);
+    }
+
+    let mut color: [f32; 3] = shape.get_color().into();
+    ui.horizontal(|ui| {
+        ui.label("Color");
+        if ui.color_edit_button_rgb(&mut color).changed() {
+            shape.set_color(color.into());
+        }
+    });
+
+    if let Some(sphere) = shape.as_any_mut().downcast_mut::<Sphere>() {
+        let mut radius = sphere.radius();
+        ui.horizontal(|ui| {
+            ui.label("Radius");
+            let drag = egui::DragValue::new(&mut radius)
+                .speed(0.02)
+                .clamp_range(0.01..=f32::MAX);
+            if ui.add(drag).changed() {
+                sphere.set_radius(radius);
+            }
+        });
+    }
+
+    if let Some(cube) = shape.as_any_mut().downcast_mut::<Cube>() {
+        let mut bounds = cube.bounds();
+        if vector_ui(ui, "Half size", &mut bounds, 0.02) {
+            cube.set_bounds(bounds.map(|b| b.max(0.0)));
+        }
+
+        let euler = Euler::from(cube.get_rotation());
+        let mut angles = Vector3::new(Deg::from(euler.x).0, Deg::from(euler.y).0, Deg::from(euler.z).0);
+        if vector_ui(ui, "Rotation", &mut angles, 1.0) {
+            let euler = Euler::new(Deg(angles.x), Deg(angles.y), Deg(angles.z));
+            cube.set_rotation(Quaternion::from(euler));
+        }
+    }
+}
+
+fn gizmo_ui(ui: &mut egui::Ui, gizmo: &mut Gizmo) {
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut gizmo.mode, GizmoMode::Translate, "Translate");
+        ui.radio_value(&mut gizmo.mode, GizmoMode::Rotate, "Rotate");
+        ui.radio_value(&mut gizmo.mode, GizmoMode::Scale, "Scale");
+    });
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut gizmo.space, GizmoSpace::World, "World");
+        ui.radio_value(&mut gizmo.space, GizmoSpace::Local, "Local");
+    });
+
+    let snapping = &mut gizmo.snapping;
+    ui.checkbox(&mut snapping.enabled, "Snap");
+    ui.add_enabled_ui(snapping.enabled, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Move");
+            ui.add(
+                egui::DragValue::new(&mut snapping.translate)
+                    .speed(0.01)
+                    .clamp_range(0.01..=f32::MAX),
+            );
+            let mut angle = Deg::from(snapping.rotate).0;
+            ui.label("Turn");
+            let turn = ui.add(
+                egui::DragValue::new(&mut angle)
+                    .speed(0.5)
+                    .clamp_range(1.0..=180.0)
+                    .suffix("°"),
+            );
+            if turn.changed() {
+                snapping.rotate = Deg(angle).into();
+            }
+            ui.label("Size");
+            ui.add(
+                egui::DragValue::new(&mut snapping.scale)
+                    .speed(0.01)
+                    .clamp_range(0.01..=f32::MAX),
+            );
+        });
+    });
+}
+
+// True if the camera was changed
+fn camera_ui(
+    ui: &mut egui::Ui,
+    camera: &mut Camera,
+    projection: &mut Projection,
+    controller: &mut CameraController,
+) -> bool {
+    let mut changed = false;
+
+    let mut pos = camera.position.to_vec();
+    if vector_ui(ui, "Position", &mut pos, 0.1) {
+        camera.position = Point3::from_vec(pos);
+        changed = true;
+    }
+
+    let (mut yaw, mut pitch) = (Deg::from(camera.yaw()).0, Deg::from(camera.pitch()).0);
+    let yaw_changed = ui
+        .horizontal(|ui| {
+            ui.label("Yaw");
+            let yaw = ui.add(egui::DragValue::new(&mut yaw).speed(0.5).suffix("°"));
+            ui.label("Pitch");
+            let pitch = ui.add(
+                egui::DragValue::new(&mut pitch)
+                    .speed(0.5)
+                    .clamp_range(-89.0..=89.0)
+                    .suffix("°"),
+            );
+            yaw.changed() || pitch.changed()
+        })
+        .inner;
+    if yaw_changed {
+        camera.set_yaw_pitch(Deg(yaw), Deg(pitch));
+        changed = true;
+    }
+
+    let mut mode = projection.mode();
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut mode, ProjectionMode::Perspective, "Perspective");
+        ui.radio_value(&mut mode, ProjectionMode::Orthographic, "Orthographic");
+    });
+    if mode != projection.mode() {
+        projection.set_mode(mode);
+    }
+
+    ui.horizontal(|ui| match mode {
+        ProjectionMode::Perspective => {
+            let mut fovy = Deg::from(projection.fovy()).0;
+            ui.label("Field of view");
+            let drag = egui::DragValue::new(&mut fovy)
+                .speed(0.5)
+                .clamp_range(1.0..=179.0)
+                .suffix("°");
+            if ui.add(drag).changed() {
+                projection.set_fovy(Deg(fovy));
+            }
+        }
+        ProjectionMode::Orthographic => {
+            let mut extent = projection.ortho_extent();
+            ui.label("Extent");
+            let drag = egui::DragValue::new(&mut extent)
+                .speed(0.05)
+                .clamp_range(0.01..=f32::MAX);
+            if ui.add(drag).changed() {
+                projection.set_ortho_extent(extent);
+            }
+        }
+    });
+
+    let mut camera_mode = controller.mode();
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut camera_mode, CameraMode::Fly, "Fly");
+        ui.radio_value(&mut camera_mode, CameraMode::Orbit, "Orbit");
+    });
+    if camera_mode != controller.mode() {
+        controller.set_mode(camera_mode, camera);
+    }
+
+    changed
+}
+
+fn params_ui(ui: &mut egui::Ui, params: &mut ShaderParams, shapes: &ShapeManager) {
+    ui.label(format!("Time: {:.2} s", params.time()));
+    ui.label(format!(
+        "{} shapes, {} spheres, {} cubes",
+        shapes.shape_count(),
+        params.sphere_count,
+        params.cube_count
+    ));
+
+    ui.horizontal(|ui| {
+        ui.label("Quality");
+        for (quality, name) in [
+            (Quality::Low, "Low"),
+            (Quality::Medium, "Medium"),
+            (Quality::High, "High"),
+            (Quality::Ultra, "Ultra"),
+        ] {
+            if ui.button(name).clicked() {
+                params.set_quality(quality);
+            }
+        }
+    });
+
+    let mut max_steps = params.max_steps();
+    ui.horizontal(|ui| {
+        ui.label("Max steps");
+        if ui
+            .add(egui::DragValue::new(&mut max_steps).clamp_range(1..=4096))
+            .changed()
+        {
+            params.set_max_steps(max_steps);
+        }
+    });
+
+    let mut epsilon = params.epsilon();
+    ui.horizontal(|ui| {
+        ui.label("Epsilon");
+        let drag = egui::DragValue::new(&mut epsilon)
+            .speed(0.00001)
+            .clamp_range(0.000001..=0.1)
+            .max_decimals(6);
+        if ui.add(drag).changed() {
+            params.set_epsilon(epsilon);
+        }
+    });
+
+    let (mut min_dist, mut max_dist) = params.march_range();
+    ui.horizontal(|ui| {
+        ui.label("March range");
+        let min = ui.add(egui::DragValue::new(&mut min_dist).speed(0.01));
+        let max = ui.add(egui::DragValue::new(&mut max_dist).speed(0.5));
+        if min.changed() || max.changed() {
+            params.set_march_range(min_dist, max_dist);
+        }
+    });
+
+    let mut relaxation = params.relaxation();
+    if ui
+        .add(egui::Slider::new(&mut relaxation, 1.0..=1.99).text("Relaxation"))
+        .changed()
+    {
+        params.set_relaxation(relaxation);
+    }
+
+    let mut footprint = params.footprint_scale();
+    if ui
+        .add(egui::Slider::new(&mut footprint, 0.0..=4.0).text("Footprint scale"))
+        .changed()
+    {
+        params.set_footprint_scale(footprint);
+    }
+
+    let (mut light_pos, mut intensity) = params.light();
+    let light_moved = vector_ui(ui, "Light", &mut light_pos, 0.2);
+    let intensity_changed = ui
+        .add(egui::Slider::new(&mut intensity, 0.0..=2.0).text("Light intensity"))
+        .changed();
+    if light_moved || intensity_changed {
+        params.set_light(light_pos, intensity);
+    }
+
+    let mut hud = params.hud();
+    if ui.checkbox(&mut hud, "Crosshair").changed() {
+        params.set_hud(hud);
+    }
+}
@@ -0,0 +1,123 @@
+use egui::epaint::textures::TexturesDelta;
+use egui::epaint::ClippedPrimitive;
+use egui_wgpu::renderer::{Renderer, ScreenDescriptor};
+use wgpu::{Device, Queue, TextureFormat};
+use winit::event::{ElementState, KeyboardInput, WindowEvent};
+use winit::event_loop::EventLoopWindowTarget;
+use winit::window::Window;
+
+// egui drawn with wgpu on top of the rendered frame. egui-winit turns winit
+// events into egui input and egui-wgpu paints the output.
+pub struct Overlay {
+    context: egui::Context,
+    state: egui_winit::State,
+    renderer: Renderer,
+
+    // output of the last run, painted by `paint`
+    primitives: Vec<ClippedPrimitive>,
+    textures_delta: TexturesDelta,
+}
+
+impl Overlay {
+    // `format` is the format of the textures the overlay is painted into.
+    pub fn new<T>(
+        event_loop: &EventLoopWindowTarget<T>,
+        device: &Device,
+        format: TextureFormat,
+        pixels_per_point: f32,
+    ) -> Self {
+        let mut state = egui_winit::State::new(event_loop);
+        state.set_pixels_per_point(pixels_per_point);
+        state.set_max_texture_side(device.limits().max_texture_dimension_2d as usize);
+        Self {
+            context: egui::Context::default(),
+            state,
+            renderer: Renderer::new(device, format, None, 1),
+            primitives: vec![],
+            textures_delta: TexturesDelta::default(),
+        }
+    }
+
+    // Window scale factor, as given by winit
+    pub fn set_pixels_per_point(&mut self, pixels_per_point: f32) {
+        self.state.set_pixels_per_point(pixels_per_point);
+    }
+
+    pub fn context(&self) -> &egui::Context {
+        &self.context
+    }
+
+    // Passes `event` on to egui. Returns true if egui uses it, in which case
+    // the rest of the app should ignore it. Releases are never used up so
+    // that nothing stays held.
+    pub fn on_event(&mut self, event: &WindowEvent) -> bool {
+        let response = self.state.on_event(&self.context, event);
+        match event {
+            WindowEvent::MouseInput { state, .. } => {
+                *state == ElementState::Pressed && response.consumed
+            }
+            // egui-winit always claims Tab to move focus, but it switches
+            // the camera mode unless a text field is being edited
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput { state, .. },
+                ..
+            } => *state == ElementState::Pressed && self.context.wants_keyboard_input(),
+            _ => response.consumed,
+        }
+    }
+
+    // Runs `ui` with the input gathered since the last run. The output is
+    // kept until `paint`.
+    pub fn run(&mut self, window: &Window, ui: impl FnOnce(&egui::Context)) {
+        let input = self.state.take_egui_input(window);
+        let output = self.context.run(input, ui);
+        self.state
+            .handle_platform_output(window, &self.context, output.platform_output);
+        self.textures_delta.append(output.textures_delta);
+        self.primitives = self.context.tessellate(output.shapes);
+    }
+
+    // Draws the output of the last run over what is already in `view`.
+    // `size` is the size of `view` in pixels.
+    pub fn paint(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size: (u32, u32),
+    ) {
+        let delta = std::mem::take(&mut self.textures_delta);
+        for (id, image) in &delta.set {
+            self.renderer.update_texture(device, queue, *id, image);
+        }
+
+        let screen = ScreenDescriptor {
+            size_in_pixels: [size.0, size.1],
+            pixels_per_point: self.context.pixels_per_point(),
+        };
+        // only paint callbacks add command buffers, and there are none
+        self.renderer
+            .update_buffers(device, queue, encoder, &self.primitives, &screen);
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Overlay Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.renderer
+                .render(&mut render_pass, &self.primitives, &screen);
+        }
+
+        for id in &delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
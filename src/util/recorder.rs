@@ -0,0 +1,183 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, RgbaImage};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RecordFormat {
+    // Numbered PNGs in a directory
+    PngSequence,
+    // Uncompressed 4:4:4 YUV that ffmpeg reads directly
+    Y4m,
+    // Looping animated GIF, only sensible for short clips
+    Gif,
+}
+
+impl RecordFormat {
+    pub fn next(self) -> Self {
+        match self {
+            RecordFormat::PngSequence => RecordFormat::Y4m,
+            RecordFormat::Y4m => RecordFormat::Gif,
+            RecordFormat::Gif => RecordFormat::PngSequence,
+        }
+    }
+
+    // File extension of the output, None for directories
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            RecordFormat::PngSequence => None,
+            RecordFormat::Y4m => Some("y4m"),
+            RecordFormat::Gif => Some("gif"),
+        }
+    }
+}
+
+enum Output {
+    Png(PathBuf),
+    Y4m(BufWriter<File>),
+    Gif(GifEncoder<BufWriter<File>>),
+}
+
+// Writes RGBA8 frames to a video file or image sequence.
+pub struct Recorder {
+    path: PathBuf,
+    format: RecordFormat,
+    width: u32,
+    height: u32,
+    fps: u32,
+    frame_count: u32,
+    output: Output,
+}
+
+impl Recorder {
+    // `path` is the output file, or directory for PNG sequences. Frames are
+    // `width` x `height` and play at `fps`.
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        format: RecordFormat,
+        width: u32,
+        height: u32,
+        fps: u32,
+    ) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let fps = fps.max(1);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let output = match format {
+            RecordFormat::PngSequence => {
+                fs::create_dir_all(&path)?;
+                Output::Png(path.clone())
+            }
+            RecordFormat::Y4m => {
+                let mut file = BufWriter::new(File::create(&path)?);
+                writeln!(
+                    file,
+                    "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444 XCOLORRANGE=LIMITED",
+                    width, height, fps
+                )?;
+                Output::Y4m(file)
+            }
+            RecordFormat::Gif => {
+                let mut encoder = GifEncoder::new(BufWriter::new(File::create(&path)?));
+                encoder.set_repeat(Repeat::Infinite).map_err(to_io)?;
+                Output::Gif(encoder)
+            }
+        };
+        Ok(Self {
+            path,
+            format,
+            width,
+            height,
+            fps,
+            frame_count: 0,
+            output,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn format(&self) -> RecordFormat {
+        self.format
+    }
+
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    // Time each frame covers, used as the fixed timestep while recording
+    pub fn frame_time(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f64(1.0 / self.fps as f64)
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    // `rgba` holds tightly packed RGBA8 rows, top row first.
+    pub fn write_frame(&mut self, rgba: &[u8]) -> io::Result<()> {
+        if rgba.len() != (self.width * self.height * 4) as usize {
+            let message = "frame size does not match the recording";
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+        match &mut self.output {
+            Output::Png(dir) => {
+                let img = RgbaImage::from_raw(self.width, self.height, rgba.to_vec()).unwrap();
+                img.save(dir.join(format!("frame_{:0>5}.png", self.frame_count + 1)))
+                    .map_err(to_io)?;
+            }
+            Output::Y4m(file) => {
+                file.write_all(b"FRAME\n")?;
+                file.write_all(&rgba_to_yuv444(rgba))?;
+            }
+            Output::Gif(encoder) => {
+                let img = RgbaImage::from_raw(self.width, self.height, rgba.to_vec()).unwrap();
+                let delay = Delay::from_numer_denom_ms(1000, self.fps);
+                encoder
+                    .encode_frame(image::Frame::from_parts(img, 0, 0, delay))
+                    .map_err(to_io)?;
+            }
+        }
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    // Flushes the output and returns the number of frames written.
+    pub fn finish(self) -> io::Result<u32> {
+        match self.output {
+            Output::Png(_) => {}
+            Output::Y4m(mut file) => file.flush()?,
+            // the encoder writes the GIF trailer when dropped
+            Output::Gif(encoder) => drop(encoder),
+        }
+        Ok(self.frame_count)
+    }
+}
+
+fn to_io(e: image::ImageError) -> io::Error {
+    io::Error::other(e)
+}
+
+// Planar Y, Cb and Cr in BT.601 limited range, alpha is dropped.
+pub fn rgba_to_yuv444(rgba: &[u8]) -> Vec<u8> {
+    let pixels = rgba.len() / 4;
+    let mut planes = vec![0; pixels * 3];
+    for (i, pixel) in rgba.chunks(4).enumerate() {
+        let (r, g, b) = (
+            pixel[0] as f32 / 255.0,
+            pixel[1] as f32 / 255.0,
+            pixel[2] as f32 / 255.0,
+        );
+        let y = 16.0 + 65.481 * r + 128.553 * g + 24.966 * b;
+        let cb = 128.0 - 37.797 * r - 74.203 * g + 112.0 * b;
+        let cr = 128.0 + 112.0 * r - 93.786 * g - 18.214 * b;
+        planes[i] = y.round() as u8;
+        planes[pixels + i] = cb.round() as u8;
+        planes[2 * pixels + i] = cr.round() as u8;
+    }
+    planes
+}
@@ -0,0 +1,360 @@
+use wgpu::util::DeviceExt;
+use wgpu::{BufferBindingType, Device, DynamicOffset, Queue, TextureFormat};
+
+use super::camera::{Camera, CameraUniform, Projection};
+use super::constructors::*;
+use super::shapes::{ShapeData, ShapeManager};
+use super::vertex::{self, VERTICES};
+use crate::ShaderParams;
+
+// The ray marching pipeline and the buffers it reads. It does not know what
+// it draws into, so the window, recordings, screenshots and headless renders
+// all go through the same path.
+pub struct SceneRenderer {
+    render_pipeline: wgpu::RenderPipeline,
+
+    // Vertex Config
+    vertex_buffer: wgpu::Buffer,
+    num_vertices: u32,
+
+    // Camera Config
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+
+    // Shader config
+    config_buffer: wgpu::Buffer,
+    config_bind_group: wgpu::BindGroup,
+
+    // Shape Config
+    shape_bind_group_layout: wgpu::BindGroupLayout,
+    shape_bind_group: wgpu::BindGroup,
+    shape_buffer: wgpu::Buffer,
+    sphere_buffer: wgpu::Buffer,
+    cube_buffer: wgpu::Buffer,
+}
+
+impl SceneRenderer {
+    // `format` is the format of the textures it will draw into. The buffers
+    // start out empty, call update before drawing.
+    pub fn new(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shader.wgsl").into()),
+        });
+
+        //#region Camera Config
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[CameraUniform::new()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bind_group_layout = create_bind_group_layout(
+            device,
+            0,
+            BufferBindingType::Uniform,
+            false,
+            None,
+            "camera_bind_group_layout",
+        );
+
+        let camera_bind_group = create_bind_group(
+            device,
+            &camera_bind_group_layout,
+            0,
+            camera_buffer.as_entire_binding(),
+            "camera_bind_group",
+        );
+        //#endregion
+
+        //#region config buffer
+        let config_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Config Buffer"),
+            contents: bytemuck::cast_slice(&[<ShaderParams as bytemuck::Zeroable>::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let config_bind_group_layout = create_bind_group_layout(
+            device,
+            1,
+            BufferBindingType::Uniform,
+            false,
+            None,
+            "config_bind_group_layout",
+        );
+
+        let config_bind_group = create_bind_group(
+            device,
+            &config_bind_group_layout,
+            1,
+            config_buffer.as_entire_binding(),
+            "config_bind_group",
+        );
+        //#endregion
+
+        //#region shape buffers
+        // an empty scene still needs one element in every buffer
+        let empty = ShapeManager::new();
+        let shape_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shape Buffer"),
+            contents: bytemuck::cast_slice(&[ShapeData::default()]),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let sphere_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sphere Buffer"),
+            contents: &empty.serialize_spheres(),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let cube_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cube Buffer"),
+            contents: &empty.serialize_cubes(),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let storage_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: true,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let shape_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[storage_entry(0), storage_entry(1), storage_entry(2)],
+                label: Some("shape_bind_group_layout"),
+            });
+
+        let shape_bind_group = create_shape_bind_group(
+            device,
+            &shape_bind_group_layout,
+            [&shape_buffer, &sphere_buffer, &cube_buffer],
+        );
+        //#endregion
+
+        //#region render pipeline
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &camera_bind_group_layout, // group 0
+                    &config_bind_group_layout, // group 1
+                    &shape_bind_group_layout,  // group 2
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[vertex::Vertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false, // based
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+        //#endregion
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        Self {
+            render_pipeline,
+            vertex_buffer,
+            num_vertices: VERTICES.len() as u32,
+            camera_buffer,
+            camera_bind_group,
+            config_buffer,
+            config_bind_group,
+            shape_bind_group_layout,
+            shape_bind_group,
+            shape_buffer,
+            sphere_buffer,
+            cube_buffer,
+        }
+    }
+
+    // Uploads the camera, `params` and the shapes, replacing the shape
+    // buffers when they are too small. `params` should have the shape counts
+    // from ShapeManager::update_shader_config.
+    pub fn update(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        shapes: &ShapeManager,
+        camera: &Camera,
+        projection: &Projection,
+        params: &ShaderParams,
+    ) {
+        let mut camera_uniform = CameraUniform::new();
+        camera_uniform.update_view_proj(camera, projection);
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[camera_uniform]));
+
+        let spheres = shapes.serialize_spheres();
+        let cubes = shapes.serialize_cubes();
+        self.reserve_shape_buffers(device, shapes, spheres.len() as u64, cubes.len() as u64);
+        self.write_params(queue, shapes, camera, projection, params);
+        queue.write_buffer(&self.sphere_buffer, 0, &spheres);
+        queue.write_buffer(&self.cube_buffer, 0, &cubes);
+    }
+
+    // Uploads `params` and the shapes' screen bounds, which depend on the
+    // size in `params`. Enough to render the same frame at another size.
+    pub fn write_params(
+        &self,
+        queue: &Queue,
+        shapes: &ShapeManager,
+        camera: &Camera,
+        projection: &Projection,
+        params: &ShaderParams,
+    ) {
+        queue.write_buffer(&self.config_buffer, 0, bytemuck::cast_slice(&[*params]));
+        queue.write_buffer(
+            &self.shape_buffer,
+            0,
+            &shapes.serialize_shapes(
+                camera.calc_matrix(),
+                projection.calc_matrix(),
+                (params.width as usize, params.height as usize),
+            ),
+        );
+    }
+
+    // Replaces the shape buffers that are too small for the shapes, which
+    // grow when shapes are added at runtime, and the bind group using them.
+    fn reserve_shape_buffers(
+        &mut self,
+        device: &Device,
+        shapes: &ShapeManager,
+        sphere_bytes: u64,
+        cube_bytes: u64,
+    ) {
+        let shape_count = shapes.shape_count().max(1) as u64;
+        let shape_bytes = shape_count * std::mem::size_of::<ShapeData>() as u64;
+        let grown = grow_buffer(device, &mut self.shape_buffer, shape_bytes, "Shape Buffer")
+            | grow_buffer(device, &mut self.sphere_buffer, sphere_bytes, "Sphere Buffer")
+            | grow_buffer(device, &mut self.cube_buffer, cube_bytes, "Cube Buffer");
+        if grown {
+            self.shape_bind_group = create_shape_bind_group(
+                device,
+                &self.shape_bind_group_layout,
+                [&self.shape_buffer, &self.sphere_buffer, &self.cube_buffer],
+            );
+        }
+    }
+
+    // Records the commands that draw the scene into `view`
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.1,
+                        g: 0.2,
+                        b: 0.3,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.render_pipeline);
+
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+
+        render_pass.set_bind_group(1, &self.config_bind_group, &[]);
+
+        render_pass.set_bind_group(
+            2,
+            &self.shape_bind_group,
+            &[
+                DynamicOffset::default(),
+                DynamicOffset::default(),
+                DynamicOffset::default(),
+            ],
+        );
+
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.num_vertices, 0..1);
+    }
+}
+
+// Bind group 2 of the render pipeline: the shapes, spheres and cubes
+fn create_shape_bind_group(
+    device: &Device,
+    layout: &wgpu::BindGroupLayout,
+    buffers: [&wgpu::Buffer; 3],
+) -> wgpu::BindGroup {
+    let [shapes, spheres, cubes] = buffers;
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: shapes.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: spheres.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: cubes.as_entire_binding(),
+            },
+        ],
+        label: Some("shape_bind_group"),
+    })
+}
+
+// Replaces `buffer` with an empty one of at least `size` bytes if it is
+// smaller. Returns true if it was replaced.
+fn grow_buffer(device: &Device, buffer: &mut wgpu::Buffer, size: u64, label: &str) -> bool {
+    if size <= buffer.size() {
+        return false;
+    }
+    *buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        // room to grow before the next replacement
+        size: size.next_power_of_two(),
+        usage: buffer.usage(),
+        mapped_at_creation: false,
+    });
+    true
+}
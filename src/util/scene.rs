@@ -0,0 +1,227 @@
+use cgmath::{Deg, Euler, Point3, Quaternion};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::camera::{Camera, Projection};
+use super::image::{self, Video};
+use super::shapes::{Shape, ShapeManager};
+use super::video_wall::VideoWall;
+
+// A scene described in a RON file, for example
+//
+// (
+//     camera: Some((position: (-10.0, 0.0, 0.0), yaw: 0.0, pitch: 0.0)),
+//     shapes: [
+//         Sphere(position: (0.0, 0.0, 0.0), radius: 1.0, color: (1.0, 0.0, 0.0)),
+//         Cube(position: (0.0, 3.0, 0.0), half_size: (1.0, 1.0, 1.0), color: (0.0, 0.5, 1.0)),
+//     ],
+//     video_wall: Some((path: "./assets/apple", size: (20, 15))),
+// )
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneFile {
+    #[serde(default)]
+    pub camera: Option<CameraDesc>,
+    #[serde(default)]
+    pub shapes: Vec<ShapeDesc>,
+    #[serde(default)]
+    pub video_wall: Option<VideoWallDesc>,
+}
+
+// Angles are in degrees
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraDesc {
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub yaw: f32,
+    #[serde(default)]
+    pub pitch: f32,
+    #[serde(default = "default_fovy")]
+    pub fovy: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ShapeDesc {
+    Sphere {
+        position: [f32; 3],
+        radius: f32,
+        color: [f32; 3],
+    },
+    Cube {
+        position: [f32; 3],
+        half_size: [f32; 3],
+        color: [f32; 3],
+        // rotation about x, y and z in degrees
+        #[serde(default)]
+        rotation: [f32; 3],
+    },
+}
+
+// See VideoWall::open
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VideoWallDesc {
+    pub path: String,
+    pub size: (u32, u32),
+    #[serde(default = "default_fps")]
+    pub fps: f32,
+    #[serde(default)]
+    pub origin: [f32; 3],
+    #[serde(default = "default_spacing")]
+    pub spacing: f32,
+    #[serde(default = "default_wall_color")]
+    pub color: [f32; 3],
+}
+
+fn default_fovy() -> f32 {
+    45.0
+}
+
+fn default_fps() -> f32 {
+    30.0
+}
+
+fn default_spacing() -> f32 {
+    2.0
+}
+
+fn default_wall_color() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+impl CameraDesc {
+    pub fn apply(&self, camera: &mut Camera, projection: &mut Projection) {
+        camera.position = Point3::from(self.position);
+        camera.set_yaw_pitch(Deg(self.yaw), Deg(self.pitch));
+        projection.set_fovy(Deg(self.fovy));
+    }
+}
+
+impl SceneFile {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        ron::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+
+    // Everything that would make the scene render wrong or not at all. Video
+    // paths are checked against the file system.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if let Some(camera) = &self.camera {
+            check_finite("camera", "position", &camera.position, &mut problems);
+            check_finite("camera", "yaw and pitch", &[camera.yaw, camera.pitch], &mut problems);
+            if !(1.0..180.0).contains(&camera.fovy) {
+                problems.push(format!("camera fovy {} is not between 1 and 180 degrees", camera.fovy));
+            }
+        }
+        for (i, shape) in self.shapes.iter().enumerate() {
+            let what = format!("shape {}", i);
+            match shape {
+                ShapeDesc::Sphere {
+                    position,
+                    radius,
+                    color,
+                } => {
+                    check_finite(&what, "position", position, &mut problems);
+                    if !(*radius > 0.0 && radius.is_finite()) {
+                        problems.push(format!("{} has a radius of {}", what, radius));
+                    }
+                    check_color(&what, color, &mut problems);
+                }
+                ShapeDesc::Cube {
+                    position,
+                    half_size,
+                    color,
+                    rotation,
+                } => {
+                    check_finite(&what, "position", position, &mut problems);
+                    check_finite(&what, "rotation", rotation, &mut problems);
+                    if half_size.iter().any(|h| !(*h > 0.0 && h.is_finite())) {
+                        problems.push(format!("{} has a half size of {:?}", what, half_size));
+                    }
+                    check_color(&what, color, &mut problems);
+                }
+            }
+        }
+        if let Some(wall) = &self.video_wall {
+            if wall.size.0 == 0 || wall.size.1 == 0 {
+                problems.push(format!("video wall size {:?} is empty", wall.size));
+            }
+            check_finite("video wall", "origin", &wall.origin, &mut problems);
+            if !(wall.fps > 0.0 && wall.fps.is_finite()) {
+                problems.push(format!("video wall fps {} is not positive", wall.fps));
+            }
+            if !(wall.spacing > 0.0 && wall.spacing.is_finite()) {
+                problems.push(format!("video wall spacing {} is not positive", wall.spacing));
+            }
+            check_color("video wall", &wall.color, &mut problems);
+            if !Path::new(&wall.path).is_file() && image::sequence_paths(&wall.path).is_empty() {
+                problems.push(format!("no video frames found at {}", wall.path));
+            }
+        }
+        problems
+    }
+
+    // Adds the shapes to `shapes` and returns the video wall, which is empty
+    // if the scene has none.
+    pub fn build(&self, shapes: &mut ShapeManager) -> io::Result<VideoWall> {
+        for shape in &self.shapes {
+            match shape {
+                ShapeDesc::Sphere {
+                    position,
+                    radius,
+                    color,
+                } => {
+                    shapes.new_sphere((*position).into(), *radius, (*color).into());
+                }
+                ShapeDesc::Cube {
+                    position,
+                    half_size,
+                    color,
+                    rotation,
+                } => {
+                    let [x, y, z] = *rotation;
+                    shapes
+                        .new_cube((*position).into(), (*half_size).into(), (*color).into())
+                        .set_rotation(Quaternion::from(Euler::new(Deg(x), Deg(y), Deg(z))));
+                }
+            }
+        }
+        match &self.video_wall {
+            Some(wall) => VideoWall::open(
+                &wall.path,
+                wall.size,
+                wall.fps,
+                wall.origin.into(),
+                wall.spacing,
+                wall.color.into(),
+                shapes,
+            ),
+            None => Ok(empty_wall(shapes)),
+        }
+    }
+}
+
+fn check_finite(what: &str, field: &str, values: &[f32], problems: &mut Vec<String>) {
+    if values.iter().any(|v| !v.is_finite()) {
+        problems.push(format!("{} has a {} that is not a finite number: {:?}", what, field, values));
+    }
+}
+
+fn check_color(what: &str, color: &[f32; 3], problems: &mut Vec<String>) {
+    if color.iter().any(|c| !(0.0..=1.0).contains(c)) {
+        problems.push(format!("{} has a color outside 0 to 1: {:?}", what, color));
+    }
+}
+
+// A wall without any cubes, for scenes without a video
+pub fn empty_wall(shapes: &mut ShapeManager) -> VideoWall {
+    let video = Video::from_frames(0, 0, vec![]);
+    VideoWall::new(video, 1.0, (0.0, 0.0, 0.0).into(), 1.0, (1.0, 1.0, 1.0).into(), shapes)
+}
@@ -0,0 +1,66 @@
+use std::io;
+use std::path::Path;
+
+use image::RgbaImage;
+
+// Averages each `factor` x `factor` block of a `width` x `height` RGBA8 image.
+// Colors are averaged in linear light so edges don't come out too dark.
+// `width` and `height` must be multiples of `factor`.
+pub fn downsample(rgba: &[u8], width: u32, height: u32, factor: u32) -> Vec<u8> {
+    assert_eq!(rgba.len(), (width * height * 4) as usize);
+    assert!(factor > 0 && width.is_multiple_of(factor) && height.is_multiple_of(factor));
+    if factor == 1 {
+        return rgba.to_vec();
+    }
+
+    let (out_width, out_height) = (width / factor, height / factor);
+    let samples = (factor * factor) as f32;
+    let mut out = Vec::with_capacity((out_width * out_height * 4) as usize);
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let mut sum = [0.0; 4];
+            for sy in y * factor..(y + 1) * factor {
+                for sx in x * factor..(x + 1) * factor {
+                    let i = ((sy * width + sx) * 4) as usize;
+                    for c in 0..3 {
+                        sum[c] += srgb_to_linear(rgba[i + c]);
+                    }
+                    sum[3] += rgba[i + 3] as f32 / 255.0;
+                }
+            }
+            for c in &sum[..3] {
+                out.push(linear_to_srgb(c / samples));
+            }
+            out.push((sum[3] / samples * 255.0).round() as u8);
+        }
+    }
+    out
+}
+
+pub fn save_png<P: AsRef<Path>>(path: P, rgba: Vec<u8>, width: u32, height: u32) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let img = RgbaImage::from_raw(width, height, rgba)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "image size does not match"))?;
+    img.save(path).map_err(io::Error::other)
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let c = value as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
@@ -1,10 +1,13 @@
 use bytemuck::Contiguous;
-use cgmath::{Quaternion, Rotation3, Vector2, Vector3};
+use cgmath::{InnerSpace, Matrix3, Quaternion, Rotation3, Vector2, Vector3};
 use std::any::Any;
 use wgpu::Device;
 
 use super::super::ShaderParams;
 
+// Must match EPSILON in shader.wgsl
+pub const EPSILON: f32 = 0.0001;
+
 pub enum Flag {
     Enabled,
 }
@@ -28,7 +31,7 @@ impl Flags {
 
     fn as_u32(&self) -> u32 {
         let mut flags = 0;
-        flags |= (self.enabled as u32) << 0;
+        flags |= self.enabled as u32;
         flags
     }
 
@@ -66,14 +69,25 @@ pub trait Shape {
 
     fn set_pos(&mut self, pos: Vector3<f32>);
 
+    fn get_pos(&self) -> Vector3<f32>;
+
     fn rotate(&mut self, rotation: Quaternion<f32>);
 
     fn set_rotation(&mut self, rotation: Quaternion<f32>);
 
+    fn get_rotation(&self) -> Quaternion<f32>;
+
     fn get_index(&self) -> u32;
 
     fn get_world_bounding_box(&self) -> (Vector3<f32>, Vector3<f32>);
 
+    // CPU-side mirror of the shape's SDF in shader.wgsl
+    fn sdf(&self, sample_point: Vector3<f32>) -> f32;
+
+    fn get_color(&self) -> Vector3<f32>;
+
+    fn set_color(&mut self, color: Vector3<f32>);
+
     fn get_screen_bounding_box(
         &self,
         inv_c_matrix: cgmath::Matrix4<f32>,
@@ -184,6 +198,14 @@ impl Sphere {
             model: [self.pos.x, self.pos.y, self.pos.z, self.radius],
         }
     }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn set_radius(&mut self, radius: f32) {
+        self.radius = radius;
+    }
 }
 
 impl Default for Sphere {
@@ -231,6 +253,10 @@ impl Shape for Sphere {
         self.pos = pos;
     }
 
+    fn get_pos(&self) -> Vector3<f32> {
+        self.pos
+    }
+
     fn rotate(&mut self, _: Quaternion<f32>) {
         // No-op
     }
@@ -239,6 +265,10 @@ impl Shape for Sphere {
         // No-op
     }
 
+    fn get_rotation(&self) -> Quaternion<f32> {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
     fn get_index(&self) -> u32 {
         self.index
     }
@@ -258,6 +288,18 @@ impl Shape for Sphere {
         )
     }
 
+    fn sdf(&self, sample_point: Vector3<f32>) -> f32 {
+        (sample_point - self.pos).magnitude() - self.radius
+    }
+
+    fn get_color(&self) -> Vector3<f32> {
+        self.color
+    }
+
+    fn set_color(&mut self, color: Vector3<f32>) {
+        self.color = color;
+    }
+
     fn get_flags(&self) -> &Flags {
         &self.flags
     }
@@ -299,6 +341,10 @@ impl Cube {
         }
     }
 
+    pub fn bounds(&self) -> Vector3<f32> {
+        self.bounds
+    }
+
     pub fn set_bounds(&mut self, bounds: Vector3<f32>) {
         self.bounds = bounds;
     }
@@ -350,6 +396,10 @@ impl Shape for Cube {
         self.pos = pos;
     }
 
+    fn get_pos(&self) -> Vector3<f32> {
+        self.pos
+    }
+
     fn rotate(&mut self, rotation: Quaternion<f32>) {
         // self.rot = rotation * self.rot;
         self.rot = self.rot * rotation;
@@ -359,23 +409,48 @@ impl Shape for Cube {
         self.rot = rotation;
     }
 
+    fn get_rotation(&self) -> Quaternion<f32> {
+        self.rot
+    }
+
     fn get_index(&self) -> u32 {
         self.index
     }
 
     fn get_world_bounding_box(&self) -> (Vector3<f32>, Vector3<f32>) {
-        (
-            Vector3::new(
-                self.pos.x - self.bounds.x,
-                self.pos.y - self.bounds.y,
-                self.pos.z - self.bounds.z,
-            ),
-            Vector3::new(
-                self.pos.x + self.bounds.x,
-                self.pos.y + self.bounds.y,
-                self.pos.z + self.bounds.z,
-            ),
-        )
+        // The shader rotates sample points into the cube's frame by `rot`, so the
+        // cube's own axes in world space are the columns of the inverse rotation.
+        let axes = Matrix3::from(self.rot.conjugate());
+        let b = self.bounds;
+        let extent = Vector3::new(
+            axes.x.x.abs() * b.x + axes.y.x.abs() * b.y + axes.z.x.abs() * b.z,
+            axes.x.y.abs() * b.x + axes.y.y.abs() * b.y + axes.z.y.abs() * b.z,
+            axes.x.z.abs() * b.x + axes.y.z.abs() * b.y + axes.z.z.abs() * b.z,
+        );
+        (self.pos - extent, self.pos + extent)
+    }
+
+    fn sdf(&self, sample_point: Vector3<f32>) -> f32 {
+        if self.bounds.x.max(self.bounds.y.max(self.bounds.z)) < EPSILON {
+            return 100.0; // arbitrary large number
+        }
+        let p = sample_point - self.pos;
+        // rotate point about center by quaternion rot
+        let q = self.rot.v;
+        let r = self.rot.s;
+        let t = 2.0 * q.cross(p);
+        let p = p + r * t + q.cross(t);
+        let d = Vector3::new(p.x.abs(), p.y.abs(), p.z.abs()) - self.bounds;
+        let outside = Vector3::new(d.x.max(0.0), d.y.max(0.0), d.z.max(0.0));
+        outside.magnitude() + d.x.max(d.y.max(d.z)).min(0.0)
+    }
+
+    fn get_color(&self) -> Vector3<f32> {
+        self.color
+    }
+
+    fn set_color(&mut self, color: Vector3<f32>) {
+        self.color = color;
     }
 
     fn get_flags(&self) -> &Flags {
@@ -447,6 +522,10 @@ impl Shape for Union {
         // No-op
     }
 
+    fn get_rotation(&self) -> Quaternion<f32> {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
     fn rotate(&mut self, _: Quaternion<f32>) {
         // No-op
     }
@@ -455,6 +534,10 @@ impl Shape for Union {
         // Unions cannot themselves be translated.
     }
 
+    fn get_pos(&self) -> Vector3<f32> {
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+
     fn translate(&mut self, _: Vector3<f32>) {
         // Unions cannot themselves be translated.
     }
@@ -463,6 +546,19 @@ impl Shape for Union {
         todo!()
     }
 
+    fn sdf(&self, _: Vector3<f32>) -> f32 {
+        // Unions are not rendered by the shader yet either.
+        f32::MAX
+    }
+
+    fn get_color(&self) -> Vector3<f32> {
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+
+    fn set_color(&mut self, _: Vector3<f32>) {
+        // Unions take their children's colors.
+    }
+
     fn get_flags(&self) -> &Flags {
         &self.flags
     }
@@ -481,6 +577,12 @@ pub struct ShapeManager {
     map: Vec<Vec<u32>>, // map of indices to shapes
 }
 
+impl Default for ShapeManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ShapeManager {
     pub fn new() -> Self {
         Self {
@@ -496,7 +598,7 @@ impl ShapeManager {
         proj_matrix: cgmath::Matrix4<f32>,
         screen_size: (usize, usize),
     ) -> Vec<u8> {
-        if self.shapes.len() == 0 {
+        if self.shapes.is_empty() {
             return bytemuck::cast_slice(&[ShapeData::default()]).to_vec();
         }
         self.shapes
@@ -619,6 +721,47 @@ impl ShapeManager {
         )
     }
 
+    // Removes the shape at `index` and returns it. Shapes after it move down
+    // one place and the shapes of its type are renumbered, so indices held
+    // elsewhere (e.g. by a VideoWall) need adjusting.
+    pub fn remove_shape(&mut self, index: u32) -> Option<Box<dyn Shape>> {
+        if index as usize >= self.shapes.len() {
+            return None;
+        }
+        let shape = self.shapes.remove(index as usize);
+        self.reindex();
+        Some(shape)
+    }
+
+    // Puts `shape` back at `index`, the inverse of remove_shape. Shapes from
+    // `index` on move up one place.
+    pub fn insert_shape(&mut self, index: u32, shape: Box<dyn Shape>) {
+        let index = (index as usize).min(self.shapes.len());
+        self.shapes.insert(index, shape);
+        self.reindex();
+    }
+
+    // Rebuilds the per-type indices from the order of `shapes`
+    fn reindex(&mut self) {
+        self.indices = [0; 1000];
+        self.map = vec![vec![], vec![]];
+        for (i, shape) in self.shapes.iter_mut().enumerate() {
+            let any = shape.as_any_mut();
+            if let Some(sphere) = any.downcast_mut::<Sphere>() {
+                sphere.index = self.indices[0];
+                self.map[0].push(i as u32);
+                self.indices[0] += 1;
+            } else if let Some(cube) = any.downcast_mut::<Cube>() {
+                cube.index = self.indices[1];
+                self.map[1].push(i as u32);
+                self.indices[1] += 1;
+            } else if let Some(union) = any.downcast_mut::<Union>() {
+                union.index = self.indices[2];
+                self.indices[2] += 1;
+            }
+        }
+    }
+
     pub fn shape_buffer_size(&self, device: &Device) -> u32 {
         let raw_size = std::mem::size_of::<ShapeData>() * self.shapes.len();
         ShapeManager::buffer_size(raw_size, device)
@@ -637,7 +780,7 @@ impl ShapeManager {
     fn buffer_size(raw_size: usize, device: &Device) -> u32 {
         let chunk_size = device.limits().min_storage_buffer_offset_alignment;
         let chunks = (raw_size as f32 / chunk_size as f32).ceil() as u32;
-        chunks * chunk_size as u32
+        chunks * chunk_size
     }
 
     pub fn update_shader_config(&self, config: &mut ShaderParams) {
@@ -654,12 +797,46 @@ impl ShapeManager {
         self.indices[0]
     }
 
+    // Union of the world bounding boxes of every rendered primitive, or None if
+    // there are none. Unions are skipped, their children already cover them.
+    pub fn world_bounding_box(&self) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        self.shapes
+            .iter()
+            .filter(|a| !a.as_any().is::<Union>())
+            .map(|a| a.get_world_bounding_box())
+            .reduce(|(min, max), (c1, c2)| {
+                (
+                    Vector3::new(min.x.min(c1.x), min.y.min(c1.y), min.z.min(c1.z)),
+                    Vector3::new(max.x.max(c2.x), max.y.max(c2.y), max.z.max(c2.z)),
+                )
+            })
+    }
+
+    // CPU-side mirror of scene_sdf in shader.wgsl: the distance to the
+    // closest enabled shape and that shape's index, if one is closer than the
+    // shader's 100.0 cut-off.
+    pub fn scene_sdf(&self, sample_point: Vector3<f32>) -> (f32, Option<u32>) {
+        let mut min_dist = 100.0;
+        let mut closest = None;
+        for (i, shape) in self.shapes.iter().enumerate() {
+            if !shape.get_flag(Flag::Enabled) || shape.as_any().is::<Union>() {
+                continue;
+            }
+            let dist = shape.sdf(sample_point);
+            if dist < min_dist {
+                min_dist = dist;
+                closest = Some(i as u32);
+            }
+        }
+        (min_dist, closest)
+    }
+
     pub fn get_shape_mut(&mut self, index: u32) -> Option<&mut Box<dyn Shape>> {
         self.shapes.get_mut(index as usize)
     }
 
-    pub fn get_shape(&self, index: u32) -> Option<&Box<dyn Shape>> {
-        self.shapes.get(index as usize)
+    pub fn get_shape(&self, index: u32) -> Option<&dyn Shape> {
+        self.shapes.get(index as usize).map(|a| a.as_ref())
     }
 
     pub fn get_sphere_mut(&mut self, index: u32) -> Option<&mut Sphere> {
@@ -667,32 +844,33 @@ impl ShapeManager {
         self.map[0]
             .get_mut(index as usize)
             .and_then(|a| self.shapes.get_mut(*a as usize))
-            .map(|a| a.as_any_mut().downcast_mut::<Sphere>())
-            .flatten()
+            .and_then(|a| a.as_any_mut().downcast_mut::<Sphere>())
     }
 
     pub fn get_sphere(&self, index: u32) -> Option<&Sphere> {
         self.map[0]
             .get(index as usize)
             .and_then(|a| self.shapes.get(*a as usize))
-            .map(|a| a.as_any().downcast_ref::<Sphere>())
-            .flatten()
+            .and_then(|a| a.as_any().downcast_ref::<Sphere>())
     }
 
     pub fn get_cube_mut(&mut self, index: u32) -> Option<&mut Cube> {
         self.map[1]
             .get_mut(index as usize)
             .and_then(|a| self.shapes.get_mut(*a as usize))
-            .map(|a| a.as_any_mut().downcast_mut::<Cube>())
-            .flatten()
+            .and_then(|a| a.as_any_mut().downcast_mut::<Cube>())
+    }
+
+    // Index in the ShapeManager (see get_shape) of the cube with index `index`
+    pub fn cube_shape_index(&self, index: u32) -> Option<u32> {
+        self.map[1].get(index as usize).copied()
     }
 
     pub fn get_cube(&self, index: u32) -> Option<&Cube> {
         self.map[1]
             .get(index as usize)
             .and_then(|a| self.shapes.get(*a as usize))
-            .map(|a| a.as_any().downcast_ref::<Cube>())
-            .flatten()
+            .and_then(|a| a.as_any().downcast_ref::<Cube>())
     }
 }
 //#endregion
@@ -0,0 +1,107 @@
+use std::fmt::Write;
+
+use super::cpu_march::encode_srgb;
+
+// How frames are drawn as text
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TerminalStyle {
+    // Upper half blocks with 24 bit ANSI colors, two pixels per character
+    TrueColor,
+    // Plain characters picked by luminance, for terminals without color
+    Ramp,
+}
+
+// Darkest to brightest
+pub const LUMINANCE_RAMP: &[u8] = b" .:-=+*#%@";
+
+impl TerminalStyle {
+    // Truecolor if the terminal says it supports it
+    pub fn detect() -> Self {
+        match std::env::var("COLORTERM").as_deref() {
+            Ok("truecolor") | Ok("24bit") => TerminalStyle::TrueColor,
+            _ => TerminalStyle::Ramp,
+        }
+    }
+}
+
+// Terminal size in characters as the tty reports it, falling back to
+// $COLUMNS and $LINES when stdout is not a terminal, and 80x24 if unset.
+pub fn terminal_size() -> (u32, u32) {
+    tty_size().unwrap_or_else(env_size)
+}
+
+#[cfg(unix)]
+fn tty_size() -> Option<(u32, u32)> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // TIOCGWINSZ only writes the window size into `size`
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_col > 0 && size.ws_row > 0)
+        .then_some((size.ws_col as u32, size.ws_row as u32))
+}
+
+#[cfg(not(unix))]
+fn tty_size() -> Option<(u32, u32)> {
+    None
+}
+
+fn env_size() -> (u32, u32) {
+    let var = |name, default| {
+        std::env::var(name)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(default)
+    };
+    (var("COLUMNS", 80), var("LINES", 24))
+}
+
+// Pixel size to render at to fill `columns` x `rows` characters. Characters
+// are about twice as tall as they are wide, so there are two pixels per row.
+pub fn pixel_size(columns: u32, rows: u32) -> (u32, u32) {
+    (columns, rows * 2)
+}
+
+fn luminance(c: [f32; 3]) -> f32 {
+    0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2]
+}
+
+// Formats a row major frame of `width` x `height` pixels, `height` even (see
+// pixel_size). Each row ends with a color reset and newline.
+pub fn format_frame(pixels: &[[f32; 3]], width: u32, height: u32, style: TerminalStyle) -> String {
+    let mut text = String::new();
+    let pixel = |x: u32, y: u32| pixels[(y * width + x) as usize];
+    for row in 0..height / 2 {
+        for x in 0..width {
+            let (top, bottom) = (pixel(x, row * 2), pixel(x, row * 2 + 1));
+            match style {
+                TerminalStyle::TrueColor => {
+                    let _ = write!(
+                        text,
+                        "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}",
+                        encode_srgb(top[0]),
+                        encode_srgb(top[1]),
+                        encode_srgb(top[2]),
+                        encode_srgb(bottom[0]),
+                        encode_srgb(bottom[1]),
+                        encode_srgb(bottom[2]),
+                    );
+                }
+                TerminalStyle::Ramp => {
+                    let l = (luminance(top) + luminance(bottom)) / 2.0;
+                    let i = (l.clamp(0.0, 1.0) * (LUMINANCE_RAMP.len() - 1) as f32).round();
+                    text.push(LUMINANCE_RAMP[i as usize] as char);
+                }
+            }
+        }
+        if style == TerminalStyle::TrueColor {
+            text.push_str("\x1b[0m");
+        }
+        text.push('\n');
+    }
+    text
+}
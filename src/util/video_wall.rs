@@ -0,0 +1,446 @@
+use cgmath::{InnerSpace, Quaternion, Rad, Rotation3, Vector3};
+
+use super::image::{Frame, Video};
+use super::shapes::{Flag, Shape, ShapeManager};
+
+// How a pixel drives its cube. Brightness is in [0, 1].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WallMapping {
+    // Scales the cube uniformly by brightness
+    Scale,
+    // Extrudes the cube out of the wall along +z, up to `height` world units,
+    // for a bar graph look
+    Height { height: f32 },
+    // Colors the cube with the pixel's RGB color
+    Color,
+    // Hides cubes darker than `threshold`
+    Visibility { threshold: f32 },
+    // Rotates the cube about `axis` by up to `max_angle`
+    Rotation { axis: Vector3<f32>, max_angle: Rad<f32> },
+}
+
+// What the wall does when playback reaches the end of the video
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlaybackMode {
+    // Stops, rewinds and puts the cubes back to rest
+    Stop,
+    Loop,
+    // Plays backwards to the start, then forwards again
+    PingPong,
+    // Stops on the last frame
+    HoldLast,
+}
+
+impl PlaybackMode {
+    pub fn next(self) -> Self {
+        match self {
+            PlaybackMode::Stop => PlaybackMode::Loop,
+            PlaybackMode::Loop => PlaybackMode::PingPong,
+            PlaybackMode::PingPong => PlaybackMode::HoldLast,
+            PlaybackMode::HoldLast => PlaybackMode::Stop,
+        }
+    }
+}
+
+// A grid of cubes driven by a video, one cube per pixel. The wall owns its
+// cubes in the ShapeManager and sets them from each frame through its
+// mappings, scaling by brightness by default.
+pub struct VideoWall {
+    video: Video,
+    fps: f32,
+    origin: Vector3<f32>,
+    spacing: f32,
+    colors: Vec<Vector3<f32>>, // resting cube colors, row major
+    mappings: Vec<WallMapping>,
+    cubes: Vec<u32>, // cube indices, row major
+    time: f32,
+    playing: bool,
+    mode: PlaybackMode,
+    reverse: bool, // playing backwards in PingPong mode
+    late_frames: u32,
+    late_frame: Option<usize>, // frame being waited on
+}
+
+impl VideoWall {
+    // Creates the wall's cubes, one per pixel of `video`, `spacing` apart in
+    // the xy plane starting at `origin`.
+    pub fn new(
+        video: Video,
+        fps: f32,
+        origin: Vector3<f32>,
+        spacing: f32,
+        color: Vector3<f32>,
+        shapes: &mut ShapeManager,
+    ) -> Self {
+        let mut cubes = vec![];
+        for y in 0..video.height() {
+            for x in 0..video.width() {
+                let pos = origin + Vector3::new(x as f32 * spacing, y as f32 * spacing, 0.0);
+                let half = spacing / 2.0;
+                let cube = shapes.new_cube(pos, (half, half, half).into(), color);
+                cubes.push(cube.get_index());
+            }
+        }
+        let mut wall = Self {
+            video,
+            fps,
+            origin,
+            spacing,
+            colors: vec![color; cubes.len()],
+            mappings: vec![WallMapping::Scale],
+            cubes,
+            time: 0.0,
+            playing: false,
+            mode: PlaybackMode::HoldLast,
+            reverse: false,
+            late_frames: 0,
+            late_frame: None,
+        };
+        if wall.frame_count() == 0 {
+            wall.apply_placeholder(shapes);
+        }
+        wall
+    }
+
+    // Streams an image sequence from a directory or file name pattern, scaled
+    // to `size` (see image::sequence_paths).
+    pub fn from_dir(
+        path: &str,
+        size: (u32, u32),
+        fps: f32,
+        origin: Vector3<f32>,
+        spacing: f32,
+        color: Vector3<f32>,
+        shapes: &mut ShapeManager,
+    ) -> Self {
+        let video = Video::new(path, size.0, size.1);
+        Self::new(video, fps, origin, spacing, color, shapes)
+    }
+
+    // Like from_dir, but also takes animated GIFs and PNGs, which play with
+    // their own frame delays, and still images (see Video::open).
+    pub fn open(
+        path: &str,
+        size: (u32, u32),
+        fps: f32,
+        origin: Vector3<f32>,
+        spacing: f32,
+        color: Vector3<f32>,
+        shapes: &mut ShapeManager,
+    ) -> std::io::Result<Self> {
+        let video = Video::open(path, size.0, size.1)?;
+        Ok(Self::new(video, fps, origin, spacing, color, shapes))
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.video.width(), self.video.height())
+    }
+
+    pub fn origin(&self) -> Vector3<f32> {
+        self.origin
+    }
+
+    pub fn spacing(&self) -> f32 {
+        self.spacing
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    pub fn mappings(&self) -> &[WallMapping] {
+        &self.mappings
+    }
+
+    // Mappings are applied in order, starting from the resting cube each frame.
+    // Rotation axes are normalized here. Rotations about a zero or non-finite
+    // axis are dropped, they would turn every cube's rotation into NaN.
+    pub fn set_mappings(&mut self, mappings: Vec<WallMapping>) {
+        self.mappings = mappings
+            .into_iter()
+            .filter_map(|mapping| match mapping {
+                WallMapping::Rotation { axis, max_angle } => {
+                    let unit = axis.normalize();
+                    if unit.x.is_finite() && unit.y.is_finite() && unit.z.is_finite() {
+                        Some(WallMapping::Rotation {
+                            axis: unit,
+                            max_angle,
+                        })
+                    } else {
+                        log::warn!("Skipping video wall rotation about {:?}", axis);
+                        None
+                    }
+                }
+                mapping => Some(mapping),
+            })
+            .collect();
+    }
+
+    // Resting color of the cube for pixel (x, y), used unless a
+    // WallMapping::Color mapping colors it from the frame.
+    pub fn set_color(&mut self, x: u32, y: u32, color: Vector3<f32>, shapes: &mut ShapeManager) {
+        if let Some(i) = self.pixel_index(x, y) {
+            self.colors[i] = color;
+            if self.frame_count() == 0 {
+                return; // keep showing the placeholder
+            }
+            if let Some(cube) = shapes.get_cube_mut(self.cubes[i]) {
+                cube.set_color(color);
+            }
+        }
+    }
+
+    fn pixel_index(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.video.width() || y >= self.video.height() {
+            return None;
+        }
+        Some((y * self.video.width() + x) as usize)
+    }
+
+    fn rest_pos(&self, x: u32, y: u32) -> Vector3<f32> {
+        self.origin + Vector3::new(x as f32 * self.spacing, y as f32 * self.spacing, 0.0)
+    }
+
+    // Cube index (see ShapeManager::get_cube) of the cube showing pixel (x, y)
+    pub fn cube_at(&self, x: u32, y: u32) -> Option<u32> {
+        self.pixel_index(x, y).and_then(|i| self.cubes.get(i).copied())
+    }
+
+    pub fn cubes(&self) -> &[u32] {
+        &self.cubes
+    }
+
+    // Keeps the wall pointing at its cubes after the cube with index `cube`
+    // was removed from the ShapeManager. The wall's own cubes must not be
+    // removed.
+    pub fn cube_removed(&mut self, cube: u32) {
+        debug_assert!(!self.cubes.contains(&cube), "removed a video wall cube");
+        for index in self.cubes.iter_mut().filter(|index| **index > cube) {
+            *index -= 1;
+        }
+    }
+
+    // Keeps the wall pointing at its cubes after a cube was inserted into the
+    // ShapeManager with index `cube`.
+    pub fn cube_inserted(&mut self, cube: u32) {
+        for index in self.cubes.iter_mut().filter(|index| **index >= cube) {
+            *index += 1;
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.video.get_frame_count()
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.video.duration(self.fps)
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn playback_mode(&self) -> PlaybackMode {
+        self.mode
+    }
+
+    // Number of frames that were not decoded in time to be shown
+    pub fn late_frames(&self) -> u32 {
+        self.late_frames
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn toggle(&mut self) {
+        self.playing = !self.playing;
+    }
+
+    pub fn set_playback_mode(&mut self, mode: PlaybackMode) {
+        self.mode = mode;
+        self.reverse = false;
+    }
+
+    // Jumps to `time` seconds into the video, clamped to its length.
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.clamp(0.0, self.duration());
+    }
+
+    // Pauses, rewinds and puts every cube back to its resting state.
+    pub fn stop(&mut self, shapes: &mut ShapeManager) {
+        self.playing = false;
+        self.time = 0.0;
+        self.reverse = false;
+        if self.frame_count() == 0 {
+            self.apply_placeholder(shapes);
+            return;
+        }
+        for y in 0..self.video.height() {
+            for x in 0..self.video.width() {
+                self.set_cube(shapes, x, y, 1.0, [0.0; 3], &[]);
+            }
+        }
+    }
+
+    pub fn update(&mut self, dt: std::time::Duration, shapes: &mut ShapeManager) {
+        if !self.playing {
+            return;
+        }
+        let duration = self.duration();
+        if duration <= 0.0 {
+            // nothing to play, keep showing the placeholder
+            self.playing = false;
+            return;
+        }
+
+        let dt = dt.as_secs_f32();
+        match self.mode {
+            PlaybackMode::Loop => self.time = (self.time + dt) % duration,
+            PlaybackMode::PingPong => {
+                // unfold the bounces into one forward pass over twice the length
+                let unfolded = if self.reverse {
+                    2.0 * duration - self.time
+                } else {
+                    self.time
+                };
+                let unfolded = (unfolded + dt) % (2.0 * duration);
+                self.reverse = unfolded > duration;
+                self.time = if self.reverse {
+                    2.0 * duration - unfolded
+                } else {
+                    unfolded
+                };
+            }
+            PlaybackMode::Stop | PlaybackMode::HoldLast => {
+                self.time += dt;
+                if self.time >= duration {
+                    if self.mode == PlaybackMode::Stop {
+                        self.stop(shapes);
+                        return;
+                    }
+                    self.time = duration;
+                    self.playing = false;
+                }
+            }
+        }
+        self.apply_frame(shapes);
+    }
+
+    // Sets every cube from the frame at the current time. If a streamed frame
+    // is not decoded yet the cubes keep showing the previous one.
+    pub fn apply_frame(&mut self, shapes: &mut ShapeManager) {
+        let frame_count = self.video.get_frame_count();
+        if frame_count == 0 {
+            self.apply_placeholder(shapes);
+            return;
+        }
+        let frame = self
+            .video
+            .frame_index_from_time(self.time, self.fps)
+            .min(frame_count - 1);
+        let pixels = match self.video.get_frame(frame) {
+            Some(pixels) => pixels,
+            None => {
+                if self.late_frame != Some(frame) {
+                    self.late_frame = Some(frame);
+                    self.late_frames += 1;
+                    eprintln!("Video frame {} is late ({} so far)", frame, self.late_frames);
+                }
+                return;
+            }
+        };
+        self.late_frame = None;
+        self.apply_pixels(&pixels, shapes);
+    }
+
+    // Like apply_frame, but waits for the frame to be decoded. For headless
+    // renders, where a late frame would be wrong rather than just late.
+    pub fn wait_frame(&mut self, shapes: &mut ShapeManager) {
+        let frame_count = self.video.get_frame_count();
+        if frame_count == 0 {
+            self.apply_placeholder(shapes);
+            return;
+        }
+        let frame = self
+            .video
+            .frame_index_from_time(self.time, self.fps)
+            .min(frame_count - 1);
+        let pixels = self.video.wait_frame(frame);
+        self.apply_pixels(&pixels, shapes);
+    }
+
+    fn apply_pixels(&self, pixels: &Frame, shapes: &mut ShapeManager) {
+        for y in 0..self.video.height() {
+            for x in 0..self.video.width() {
+                let (p, color) = (pixels.luminance(x, y), pixels.color(x, y));
+                self.set_cube(shapes, x, y, p, color, &self.mappings);
+            }
+        }
+    }
+
+    // Colors the resting cubes with a checkerboard to show the video is
+    // missing.
+    fn apply_placeholder(&self, shapes: &mut ShapeManager) {
+        let placeholder = Frame::placeholder(self.video.width(), self.video.height());
+        for y in 0..self.video.height() {
+            for x in 0..self.video.width() {
+                let color = placeholder.color(x, y);
+                self.set_cube(shapes, x, y, 1.0, color, &[WallMapping::Color]);
+            }
+        }
+    }
+
+    // Puts the cube for pixel (x, y) in its resting state, then applies each
+    // mapping for brightness `p`.
+    fn set_cube(
+        &self,
+        shapes: &mut ShapeManager,
+        x: u32,
+        y: u32,
+        p: f32,
+        color: [f32; 3],
+        mappings: &[WallMapping],
+    ) {
+        let Some(i) = self.pixel_index(x, y) else {
+            return;
+        };
+        let half = self.spacing / 2.0;
+        let mut pos = self.rest_pos(x, y);
+        let mut bounds = Vector3::new(half, half, half);
+        let mut rot = Quaternion::from_angle_z(Rad(0.0));
+        let mut cube_color = self.colors[i];
+        let mut enabled = true;
+        for mapping in mappings {
+            match *mapping {
+                WallMapping::Scale => bounds *= p,
+                WallMapping::Height { height } => {
+                    // keep the base of the bar on the wall
+                    bounds.z = p * height / 2.0;
+                    pos.z = self.origin.z - half + bounds.z;
+                }
+                WallMapping::Color => cube_color = color.into(),
+                WallMapping::Visibility { threshold } => enabled = p >= threshold,
+                WallMapping::Rotation { axis, max_angle } => {
+                    rot = Quaternion::from_axis_angle(axis, max_angle * p)
+                }
+            }
+        }
+
+        if let Some(cube) = shapes.get_cube_mut(self.cubes[i]) {
+            cube.set_pos(pos);
+            cube.set_bounds(bounds);
+            cube.set_rotation(rot);
+            cube.set_color(cube_color);
+            cube.set_flag(Flag::Enabled, enabled);
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use ray_marching::util::adapter::{parse_backends, GpuError};
+use wgpu::Backends;
+
+#[test]
+fn backends_by_name() {
+    assert_eq!(parse_backends("vulkan").unwrap(), Backends::VULKAN);
+    assert_eq!(parse_backends("VK").unwrap(), Backends::VULKAN);
+    assert_eq!(parse_backends("dx12").unwrap(), Backends::DX12);
+    assert_eq!(parse_backends("gl").unwrap(), Backends::GL);
+    assert_eq!(parse_backends("all").unwrap(), Backends::all());
+
+    let error = parse_backends("glide").unwrap_err();
+    assert!(matches!(&error, GpuError::UnknownBackend(name) if name == "glide"));
+    assert!(error.to_string().contains("'glide'"));
+}
+
+#[test]
+fn errors_name_the_adapter() {
+    let error = GpuError::NoAdapter {
+        backends: Backends::VULKAN,
+        name: Some("radeon".into()),
+    };
+    assert!(error.to_string().contains("'radeon'"));
+    assert!(error.to_string().contains("--list-adapters"));
+}
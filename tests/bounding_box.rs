@@ -0,0 +1,91 @@
+use cgmath::{Deg, Quaternion, Rotation3, Vector3};
+use ray_marching::util::shapes::{Shape, ShapeManager};
+
+// Samples a grid around the shape and checks that every point the CPU SDF
+// considers inside (or on) the surface lies within the world bounding box.
+fn assert_bounds_conservative(shape: &dyn Shape, reach: f32) {
+    let (min, max) = shape.get_world_bounding_box();
+    let steps = 40;
+    for i in 0..=steps {
+        for j in 0..=steps {
+            for k in 0..=steps {
+                let t = |n: i32| -reach + 2.0 * reach * n as f32 / steps as f32;
+                let p = Vector3::new(t(i), t(j), t(k));
+                if shape.sdf(p) > 0.0 {
+                    continue;
+                }
+                assert!(
+                    p.x >= min.x - 1e-4
+                        && p.y >= min.y - 1e-4
+                        && p.z >= min.z - 1e-4
+                        && p.x <= max.x + 1e-4
+                        && p.y <= max.y + 1e-4
+                        && p.z <= max.z + 1e-4,
+                    "{:?} is inside the shape but outside {:?}..{:?}",
+                    p,
+                    min,
+                    max
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn unrotated_cube_bounds() {
+    let mut shapes = ShapeManager::new();
+    shapes.new_cube(
+        (0.0, 0.0, 0.0).into(),
+        (1.0, 2.0, 3.0).into(),
+        (1.0, 1.0, 1.0).into(),
+    );
+    let (min, max) = shapes.get_shape(0).unwrap().get_world_bounding_box();
+    assert!((min - Vector3::new(-1.0, -2.0, -3.0)).x.abs() < 1e-5);
+    assert!((max - Vector3::new(1.0, 2.0, 3.0)).z.abs() < 1e-5);
+    assert_bounds_conservative(shapes.get_shape(0).unwrap(), 4.0);
+}
+
+#[test]
+fn rotated_cube_bounds_are_conservative() {
+    let rotations = [
+        Quaternion::from_angle_z(Deg(45.0)),
+        Quaternion::from_angle_x(Deg(30.0)),
+        Quaternion::from_angle_y(Deg(-60.0)),
+        Quaternion::from_angle_x(Deg(35.0)) * Quaternion::from_angle_y(Deg(45.0)),
+        Quaternion::from_axis_angle(Vector3::new(1.0, 1.0, 1.0) / 3f32.sqrt(), Deg(70.0)),
+    ];
+    for rot in rotations {
+        let mut shapes = ShapeManager::new();
+        shapes.new_cube(
+            (0.0, 0.0, 0.0).into(),
+            (1.0, 0.5, 2.0).into(),
+            (1.0, 1.0, 1.0).into(),
+        );
+        shapes.get_shape_mut(0).unwrap().rotate(rot);
+        assert_bounds_conservative(shapes.get_shape(0).unwrap(), 3.0);
+    }
+}
+
+#[test]
+fn rotated_cube_bounds_grow() {
+    // A unit cube rotated 45 degrees about z reaches sqrt(2) along x and y.
+    let mut shapes = ShapeManager::new();
+    shapes
+        .new_cube(
+            (2.0, 0.0, 0.0).into(),
+            (1.0, 1.0, 1.0).into(),
+            (1.0, 1.0, 1.0).into(),
+        )
+        .rotate(Quaternion::from_angle_z(Deg(45.0)));
+    let (min, max) = shapes.get_shape(0).unwrap().get_world_bounding_box();
+    assert!((max.x - (2.0 + 2f32.sqrt())).abs() < 1e-4);
+    assert!((min.y + 2f32.sqrt()).abs() < 1e-4);
+    assert!((max.z - 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn sphere_bounds_are_conservative() {
+    let mut shapes = ShapeManager::new();
+    shapes.new_sphere((0.5, -0.5, 0.0).into(), 1.5, (1.0, 1.0, 1.0).into());
+    assert_bounds_conservative(shapes.get_shape(0).unwrap(), 3.0);
+}
@@ -0,0 +1,100 @@
+use cgmath::{Deg, InnerSpace, MetricSpace, Vector3};
+use ray_marching::util::camera::{
+    Camera, CameraController, CameraMode, Projection, SAFE_FRAC_PI_2,
+};
+use std::time::Duration;
+
+const DT: Duration = Duration::from_millis(16);
+
+#[test]
+fn switching_modes_does_not_move_the_camera() {
+    let mut camera = Camera::new((-10.0, 2.0, 3.0), Deg(30.0), Deg(-20.0));
+    let mut controller = CameraController::new(4.0, 0.25);
+    let (position, yaw, pitch) = (camera.position, camera.yaw(), camera.pitch());
+
+    controller.set_mode(CameraMode::Orbit, &camera);
+    controller.update_camera(&mut camera, DT);
+    assert!(camera.position.distance(position) < 1e-4);
+    assert_eq!((camera.yaw(), camera.pitch()), (yaw, pitch));
+
+    controller.set_mode(CameraMode::Fly, &camera);
+    controller.update_camera(&mut camera, DT);
+    assert!(camera.position.distance(position) < 1e-4);
+    assert_eq!((camera.yaw(), camera.pitch()), (yaw, pitch));
+}
+
+#[test]
+fn orbit_keeps_distance_to_target() {
+    let mut camera = Camera::new((-10.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+    let mut controller = CameraController::new(4.0, 0.25);
+    controller.set_mode(CameraMode::Orbit, &camera);
+    let target = controller.orbit_target();
+
+    for _ in 0..20 {
+        controller.process_mouse(30.0, -10.0);
+        controller.update_camera(&mut camera, DT);
+        assert!((camera.position.distance(target) - controller.orbit_distance()).abs() < 1e-3);
+        // the camera keeps looking at the target
+        let to_target = (target - camera.position).normalize();
+        assert!(to_target.dot(camera.forward()) > 0.9999);
+    }
+}
+
+#[test]
+fn framing_fits_the_box_in_view() {
+    let mut camera = Camera::new((-10.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+    let mut projection = Projection::new(800, 600, Deg(45.0), 0.1, 100.0);
+    let mut controller = CameraController::new(4.0, 0.25);
+    let bounds = (Vector3::new(4.0, -1.0, -1.0), Vector3::new(6.0, 1.0, 1.0));
+
+    controller.frame(&mut camera, &mut projection, bounds);
+    assert_eq!(controller.mode(), CameraMode::Orbit);
+    assert_eq!(controller.orbit_target(), (5.0, 0.0, 0.0).into());
+
+    // the bounding sphere of the box must fit inside the vertical fov
+    let radius = 3f32.sqrt();
+    let half_angle = (radius / controller.orbit_distance()).asin();
+    assert!(half_angle <= projection.fovy().0 / 2.0 + 1e-4);
+}
+
+fn assert_matrix_eq(a: cgmath::Matrix4<f32>, b: cgmath::Matrix4<f32>) {
+    let (a, b): ([[f32; 4]; 4], [[f32; 4]; 4]) = (a.into(), b.into());
+    for (ca, cb) in a.iter().zip(b.iter()) {
+        for (x, y) in ca.iter().zip(cb.iter()) {
+            assert!((x - y).abs() < 1e-4, "{:?} != {:?}", a, b);
+        }
+    }
+}
+
+#[test]
+fn free_mode_keeps_the_view() {
+    let mut camera = Camera::new((1.0, 2.0, 3.0), Deg(40.0), Deg(-30.0));
+    let view = camera.calc_matrix();
+
+    camera.set_free(true);
+    assert!(camera.is_free());
+    assert_matrix_eq(camera.calc_matrix(), view);
+    assert!((camera.yaw().0 - cgmath::Rad::from(Deg(40.0)).0).abs() < 1e-4);
+
+    camera.set_free(false);
+    assert_matrix_eq(camera.calc_matrix(), view);
+}
+
+#[test]
+fn free_camera_can_look_straight_down_and_roll() {
+    let mut camera = Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+    camera.turn(Deg(0.0).into(), Deg(-90.0).into());
+    // yaw/pitch cameras stop at SAFE_FRAC_PI_2
+    assert!(camera.forward().y > -0.99);
+
+    camera.set_free(true);
+    let remaining = std::f32::consts::FRAC_PI_2 - SAFE_FRAC_PI_2;
+    camera.turn(Deg(0.0).into(), cgmath::Rad(-remaining));
+    assert!((camera.forward() - Vector3::new(0.0, -1.0, 0.0)).magnitude() < 1e-3);
+
+    let forward = camera.forward();
+    let up = camera.up();
+    camera.roll(Deg(90.0).into());
+    assert!((camera.forward() - forward).magnitude() < 1e-4);
+    assert!(camera.up().dot(up).abs() < 1e-4);
+}
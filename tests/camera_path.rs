@@ -0,0 +1,91 @@
+use ray_marching::util::camera_path::{CameraPath, Keyframe};
+
+fn keyframe(time: f32, x: f32, yaw: f32) -> Keyframe {
+    Keyframe {
+        time,
+        position: [x, 0.0, 0.0],
+        yaw,
+        pitch: 0.0,
+        fovy: 0.8,
+        orientation: None,
+    }
+}
+
+fn path() -> CameraPath {
+    let mut path = CameraPath::new();
+    // added out of order on purpose
+    path.add_keyframe(keyframe(3.0, 4.0, 1.0));
+    path.add_keyframe(keyframe(0.0, 0.0, 0.0));
+    path.add_keyframe(keyframe(1.0, 2.0, 0.5));
+    path.add_keyframe(keyframe(5.0, 4.0, 2.0));
+    path
+}
+
+#[test]
+fn spline_passes_through_keyframes() {
+    let path = path();
+    assert_eq!(path.len(), 4);
+    for k in path.keyframes() {
+        let s = path.sample(k.time).unwrap();
+        assert!((s.position[0] - k.position[0]).abs() < 1e-5);
+        assert!((s.yaw - k.yaw).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn spline_is_continuous_across_keyframes() {
+    let path = path();
+    for k in path.keyframes() {
+        let before = path.sample(k.time - 1e-3).unwrap();
+        let after = path.sample(k.time + 1e-3).unwrap();
+        assert!((before.position[0] - after.position[0]).abs() < 1e-2);
+        assert!((before.yaw - after.yaw).abs() < 1e-2);
+    }
+}
+
+#[test]
+fn yaw_turns_the_short_way_across_a_half_turn() {
+    // the angle between two yaws, ignoring whole turns
+    let turn = |a: f32, b: f32| (a - b).sin().atan2((a - b).cos()).abs();
+    // crossing 0° and crossing ±180°
+    for (yaws, middle) in [([330.0, 350.0, 10.0, 30.0], 0.0), ([150.0, 170.0, -170.0, -150.0], 180.0)] {
+        let mut path = CameraPath::new();
+        for (i, yaw) in yaws.iter().enumerate() {
+            path.add_keyframe(keyframe(i as f32, 0.0, f32::to_radians(*yaw)));
+        }
+        let yaw = path.sample(1.5).unwrap().yaw;
+        assert!(turn(yaw, f32::to_radians(middle)) < 1e-3, "{}", yaw.to_degrees());
+        for t in 0..60 {
+            let a = path.sample(t as f32 * 0.05).unwrap().yaw;
+            let b = path.sample((t + 1) as f32 * 0.05).unwrap().yaw;
+            assert!(turn(a, b) < 0.1, "the camera spun between {} and {}", a, b);
+        }
+    }
+}
+
+#[test]
+fn sampling_clamps_to_the_ends() {
+    let path = path();
+    assert_eq!(path.sample(-1.0).unwrap().position, [0.0, 0.0, 0.0]);
+    assert_eq!(path.sample(10.0).unwrap().yaw, 2.0);
+    assert_eq!(path.duration(), 5.0);
+    assert!(CameraPath::new().sample(0.0).is_none());
+}
+
+#[test]
+fn keyframes_at_the_same_time_are_replaced() {
+    let mut path = path();
+    path.add_keyframe(keyframe(1.0, 9.0, 0.0));
+    assert_eq!(path.len(), 4);
+    assert_eq!(path.sample(1.0).unwrap().position[0], 9.0);
+}
+
+#[test]
+fn round_trips_through_a_file() {
+    let file = std::env::temp_dir().join(format!("camera_path_{}.ron", std::process::id()));
+    let path = path();
+    path.save(&file).unwrap();
+    let loaded = CameraPath::load(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+    assert_eq!(loaded, path);
+}
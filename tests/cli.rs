@@ -0,0 +1,108 @@
+use ray_marching::util::cli::{self, Command, WindowOptions};
+use ray_marching::util::recorder::RecordFormat;
+use ray_marching::util::terminal::TerminalStyle;
+use ray_marching::Quality;
+use wgpu::{Backends, PresentMode};
+
+fn parse(args: &str) -> Result<Command, String> {
+    cli::parse(args.split_whitespace().map(String::from))
+}
+
+#[test]
+fn no_arguments_open_the_window() {
+    assert_eq!(parse("").unwrap(), Command::Window(WindowOptions::default()));
+}
+
+#[test]
+fn window_options() {
+    let Command::Window(options) = parse(
+        "--scene s.ron --size=800x600 --fullscreen --present-mode mailbox \
+         --quality ULTRA --record-format gif --screenshot-scale 2 --backend=vulkan \
+         --adapter nv --software",
+    )
+    .unwrap() else {
+        panic!("expected window options");
+    };
+    assert_eq!(options.scene.unwrap().to_str(), Some("s.ron"));
+    assert_eq!(options.size, Some((800, 600)));
+    assert!(options.fullscreen);
+    assert_eq!(options.present_mode, PresentMode::Mailbox);
+    assert_eq!(options.quality, Quality::Ultra);
+    assert_eq!(options.record_format, RecordFormat::Gif);
+    assert_eq!(options.screenshot_scale, 2);
+    assert_eq!(options.adapter.backends, Backends::VULKAN);
+    assert_eq!(options.adapter.name.as_deref(), Some("nv"));
+    assert!(options.adapter.software);
+}
+
+#[test]
+fn other_modes() {
+    assert_eq!(
+        parse("--backend gl --list-adapters").unwrap(),
+        Command::ListAdapters(Backends::GL)
+    );
+    assert_eq!(
+        parse("--terminal=ramp --scene a.ron").unwrap(),
+        Command::Terminal {
+            style: TerminalStyle::Ramp,
+            scene: Some("a.ron".into()),
+        }
+    );
+    assert_eq!(parse("validate a.ron").unwrap(), Command::Validate("a.ron".into()));
+    assert_eq!(parse("--help").unwrap(), Command::Help);
+}
+
+#[test]
+fn render_options() {
+    let Command::Render(options) = parse(
+        "render --out f.png --size 64x32 --camera 1,2,3,90,-10 --time 2.5 --quality low \
+         --backend gl --adapter llvm --software",
+    )
+    .unwrap() else {
+        panic!("expected render options");
+    };
+    assert_eq!(options.out.to_str(), Some("f.png"));
+    assert_eq!(options.size, (64, 32));
+    let camera = options.camera.unwrap();
+    assert_eq!(camera.position, [1.0, 2.0, 3.0]);
+    assert_eq!((camera.yaw, camera.pitch, camera.fovy), (90.0, -10.0, 45.0));
+    assert_eq!(options.time, 2.5);
+    assert_eq!(options.quality, Quality::Low);
+    assert_eq!(options.adapter.backends, Backends::GL);
+    assert_eq!(options.adapter.name.as_deref(), Some("llvm"));
+    assert!(options.adapter.software);
+
+    let Command::Render(options) = parse("render -o f.png").unwrap() else {
+        panic!("expected render options");
+    };
+    assert_eq!(options.size, (1280, 720));
+    assert_eq!(options.camera, None);
+}
+
+#[test]
+fn bad_arguments() {
+    for args in [
+        "--nope",
+        "--size 800",
+        "--size 0x600",
+        "--quality extreme",
+        "--present-mode never",
+        "--backend glide",
+        "--screenshot-scale 0",
+        "--screenshot-scale 1.5",
+        "--scene",
+        "--fullscreen=yes",
+        "--terminal=sixel",
+        "render",
+        "render --out f.png --camera 1,2",
+        "render --out f.png --time soon",
+        "render --out f.png --time inf",
+        "render --out f.png --camera nan,0,0",
+        "render --out f.png --camera 0,0,0,-inf,0",
+        "render --out f.png --backend glide",
+        "validate",
+        "validate a.ron b.ron",
+    ] {
+        assert!(parse(args).is_err(), "{} should not parse", args);
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use ray_marching::util::clock::{Clock, DEFAULT_STEP, MAX_TIME_SCALE};
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn clock_follows_real_time_scaled() {
+    let mut clock = Clock::new();
+    assert_eq!(clock.tick(ms(100)), ms(100));
+    clock.set_time_scale(0.5);
+    assert_eq!(clock.tick(ms(100)), ms(50));
+    assert!((clock.time() - 0.15).abs() < 1e-6);
+
+    clock.set_time_scale(1000.0);
+    assert_eq!(clock.time_scale(), MAX_TIME_SCALE);
+}
+
+#[test]
+fn paused_clock_only_advances_by_steps() {
+    let mut clock = Clock::new();
+    clock.pause();
+    assert_eq!(clock.tick(ms(100)), Duration::ZERO);
+    assert_eq!(clock.time(), 0.0);
+
+    clock.step();
+    clock.step();
+    assert_eq!(clock.tick(ms(100)), DEFAULT_STEP);
+    assert_eq!(clock.tick(ms(100)), DEFAULT_STEP);
+    assert_eq!(clock.tick(ms(100)), Duration::ZERO);
+    assert!(clock.is_paused());
+
+    clock.set_fixed_step(Some(ms(40)));
+    clock.step();
+    assert_eq!(clock.tick(ms(100)), ms(40));
+
+    clock.resume();
+    assert_eq!(clock.tick(ms(100)), ms(40));
+}
+
+#[test]
+fn fixed_step_is_reproducible() {
+    let run = |frame_times: &[u64]| {
+        let mut clock = Clock::new();
+        clock.set_fixed_step(Some(ms(20)));
+        for &t in frame_times {
+            clock.tick(ms(t));
+        }
+        clock.time()
+    };
+    assert_eq!(run(&[1, 50, 7, 300]), run(&[16, 16, 16, 16]));
+    assert!((run(&[1, 2, 3]) - 0.06).abs() < 1e-6);
+}
+
+#[test]
+fn seek_returns_the_jump() {
+    let mut clock = Clock::new();
+    clock.tick(ms(500));
+    assert!((clock.seek(2.0) - 1.5).abs() < 1e-6);
+    assert_eq!(clock.time(), 2.0);
+    // scene time never goes negative
+    assert!((clock.seek(-1.0) + 2.0).abs() < 1e-6);
+    assert_eq!(clock.time(), 0.0);
+}
@@ -0,0 +1,119 @@
+use cgmath::{Deg, InnerSpace};
+use ray_marching::util::camera::{Camera, Projection, ProjectionMode};
+use ray_marching::util::cpu_march::{self, Ray, BACKGROUND, OUTLINE_COLOR};
+use ray_marching::util::shapes::ShapeManager;
+use ray_marching::util::terminal::{self, TerminalStyle};
+use ray_marching::ShaderParams;
+
+// A red unit sphere 10 units in front of a camera looking down +x
+fn scene() -> (ShapeManager, Camera, Projection) {
+    let mut shapes = ShapeManager::new();
+    shapes.new_sphere((0.0, 0.0, 0.0).into(), 1.0, (1.0, 0.0, 0.0).into());
+    let camera = Camera::new((-10.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+    let projection = Projection::new(16, 16, Deg(45.0), 0.1, 100.0);
+    (shapes, camera, projection)
+}
+
+#[test]
+fn center_ray_hits_the_sphere() {
+    let (shapes, camera, projection) = scene();
+    let params = ShaderParams::new(16, 16, &projection);
+    let ray = Ray::through_pixel(&camera, &projection, (16, 16), (8.0, 8.0));
+    assert!((ray.dir - camera.forward()).magnitude() < 1e-5);
+
+    let range = cpu_march::march_range(&params, &projection);
+    let hit = cpu_march::march(&shapes, &ray, range, &params).unwrap();
+    assert_eq!(hit.shape, 0);
+    assert!((hit.depth - 9.0).abs() < 0.01);
+
+    let ray = Ray::through_pixel(&camera, &projection, (16, 16), (0.5, 0.5));
+    assert!(cpu_march::march(&shapes, &ray, range, &params).is_none());
+}
+
+#[test]
+fn render_matches_the_scene() {
+    let (shapes, camera, mut projection) = scene();
+    for mode in [ProjectionMode::Perspective, ProjectionMode::Orthographic] {
+        projection.set_mode(mode);
+        projection.set_ortho_extent(2.0);
+        let params = ShaderParams::new(16, 16, &projection);
+        let pixels = cpu_march::render(&shapes, &camera, &projection, &params, 16, 16);
+        assert_eq!(pixels.len(), 256);
+        let center = pixels[8 * 16 + 8];
+        // the green and blue the sphere lacks still go through the sRGB curve
+        let unlit = cpu_march::rgb_to_srgb(cgmath::Vector3::new(0.0, 0.0, 0.0)).x;
+        assert!(center[0] > 0.04 && center[1] == unlit && center[2] == unlit, "{:?}", center);
+        assert_eq!(pixels[0], [BACKGROUND.x, BACKGROUND.y, BACKGROUND.z]);
+    }
+}
+
+#[test]
+fn shading_uses_the_shaders_srgb_curve() {
+    let srgb = cpu_march::rgb_to_srgb(cgmath::Vector3::new(0.0, 0.5, 1.0));
+    assert!((srgb.x - 0.000834).abs() < 1e-6, "{:?}", srgb);
+    assert!((srgb.y - 0.214041).abs() < 1e-5, "{:?}", srgb);
+    assert!((srgb.z - 1.0).abs() < 1e-6, "{:?}", srgb);
+
+    // lit pixels are converted, the background is not
+    let (shapes, camera, projection) = scene();
+    let params = ShaderParams::new(16, 16, &projection);
+    let ray = Ray::through_pixel(&camera, &projection, (16, 16), (8.5, 8.5));
+    let range = cpu_march::march_range(&params, &projection);
+    let hit = cpu_march::march(&shapes, &ray, range, &params).unwrap();
+    let shaded = cpu_march::shade(&shapes, &hit, ray.origin, &params);
+    let pixels = cpu_march::render(&shapes, &camera, &projection, &params, 16, 16);
+    assert_eq!(pixels[8 * 16 + 8], [shaded.x, shaded.y, shaded.z]);
+    assert_eq!(shaded.y, srgb.x);
+}
+
+#[test]
+fn disabled_shapes_are_not_drawn() {
+    use ray_marching::util::shapes::{Flag, Shape};
+    let (mut shapes, camera, projection) = scene();
+    shapes.get_sphere_mut(0).unwrap().set_flag(Flag::Enabled, false);
+    let params = ShaderParams::new(16, 16, &projection);
+    let pixels = cpu_march::render(&shapes, &camera, &projection, &params, 16, 16);
+    assert_eq!(pixels[8 * 16 + 8], [BACKGROUND.x, BACKGROUND.y, BACKGROUND.z]);
+}
+
+#[test]
+fn pick_returns_the_shape_under_the_cursor() {
+    let (shapes, camera, projection) = scene();
+    let params = ShaderParams::new(16, 16, &projection);
+    let hit = cpu_march::pick(&shapes, &camera, &projection, &params, (16, 16), (8.0, 8.0)).unwrap();
+    assert_eq!(hit.shape, 0);
+    assert!((hit.point - cgmath::Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 0.01);
+    assert!(cpu_march::pick(&shapes, &camera, &projection, &params, (16, 16), (0.5, 0.5)).is_none());
+}
+
+#[test]
+fn selected_shape_is_outlined() {
+    let (shapes, camera, projection) = scene();
+    let mut params = ShaderParams::new(32, 32, &projection);
+    let outline = [OUTLINE_COLOR.x, OUTLINE_COLOR.y, OUTLINE_COLOR.z];
+    let outlined = |params: &ShaderParams| {
+        let pixels = cpu_march::render(&shapes, &camera, &projection, params, 32, 32);
+        assert_ne!(pixels[16 * 32 + 16], outline);
+        assert_ne!(pixels[0], outline);
+        pixels.iter().filter(|pixel| **pixel == outline).count()
+    };
+    assert_eq!(outlined(&params), 0);
+
+    params.set_selected(Some(0));
+    assert!(outlined(&params) > 0);
+
+    params.set_hud(false);
+    assert_eq!(outlined(&params), 0);
+}
+
+#[test]
+fn terminal_frames() {
+    let pixels = [[0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0]];
+    assert_eq!(terminal::format_frame(&pixels, 2, 2, TerminalStyle::Ramp), " +\n");
+    let text = terminal::format_frame(&pixels, 2, 2, TerminalStyle::TrueColor);
+    assert_eq!(
+        text,
+        "\x1b[38;2;0;0;0m\x1b[48;2;0;0;0m\u{2580}\x1b[38;2;255;255;255m\x1b[48;2;255;0;0m\u{2580}\x1b[0m\n"
+    );
+    assert_eq!(terminal::pixel_size(80, 23), (80, 46));
+}
@@ -0,0 +1,168 @@
+use cgmath::{Deg, InnerSpace, Rad, Vector3};
+use ray_marching::util::camera::{Camera, Projection};
+use ray_marching::util::gizmo::{Gizmo, GizmoMode, GizmoSpace, Handle, Viewport, GIZMO_SIZE};
+use ray_marching::util::shapes::{Cube, Shape, Sphere};
+
+const SIZE: (u32, u32) = (400, 300);
+
+// A camera 10 units from the origin looking down +x
+fn camera() -> (Camera, Projection) {
+    let camera = Camera::new((-10.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+    let projection = Projection::new(SIZE.0, SIZE.1, Deg(45.0), 0.1, 100.0);
+    (camera, projection)
+}
+
+// Pixel `pixels` along the handle of `axis`, in the middle of the arrows
+fn along(
+    view: &Viewport,
+    gizmo: &Gizmo,
+    shape: &dyn Shape,
+    axis: usize,
+    pixels: f32,
+) -> (f32, f32) {
+    let direction = gizmo.axes(shape)[axis];
+    let distance = pixels * view.pixel_size(shape.get_pos());
+    view.project(shape.get_pos() + direction * distance)
+        .unwrap()
+}
+
+fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+    assert!((a - b).magnitude() < 1e-3, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn project_inverts_rays() {
+    let (camera, projection) = camera();
+    let view = Viewport {
+        camera: &camera,
+        projection: &projection,
+        size: SIZE,
+    };
+    for coord in [(200.0, 150.0), (13.5, 280.0), (390.0, 2.0)] {
+        let ray = view.ray(coord);
+        let (x, y) = view.project(ray.at(7.0)).unwrap();
+        assert!((x - coord.0).abs() < 1e-2 && (y - coord.1).abs() < 1e-2);
+    }
+    assert!(view.project((-20.0, 0.0, 0.0).into()).is_none());
+}
+
+#[test]
+fn handles_are_hit_near_their_arrows() {
+    let (camera, projection) = camera();
+    let view = Viewport {
+        camera: &camera,
+        projection: &projection,
+        size: SIZE,
+    };
+    let cube = Cube::default();
+    let gizmo = Gizmo::default();
+    let hit = gizmo.hit(
+        &view,
+        &cube,
+        along(&view, &gizmo, &cube, 2, GIZMO_SIZE * 0.7),
+    );
+    assert_eq!(
+        hit,
+        Some(Handle {
+            mode: GizmoMode::Translate,
+            axis: 2
+        })
+    );
+    assert_eq!(gizmo.hit(&view, &cube, (5.0, 5.0)), None);
+}
+
+#[test]
+fn translate_snaps_along_the_axis() {
+    let (camera, projection) = camera();
+    let view = Viewport {
+        camera: &camera,
+        projection: &projection,
+        size: SIZE,
+    };
+    let mut cube = Cube::default();
+    let mut gizmo = Gizmo::default();
+    gizmo.snapping.enabled = true;
+    gizmo.snapping.translate = 0.5;
+
+    let start = along(&view, &gizmo, &cube, 1, GIZMO_SIZE * 0.5);
+    assert!(gizmo.begin_drag(&view, &cube, start));
+    let pixel = view.pixel_size(cube.get_pos());
+    let end = along(&view, &gizmo, &cube, 1, GIZMO_SIZE * 0.5 + 1.3 / pixel);
+    gizmo.drag(&view, &mut cube, end);
+    assert_close(cube.get_pos(), Vector3::new(0.0, 1.5, 0.0));
+
+    // dragging back undoes the move
+    gizmo.drag(&view, &mut cube, start);
+    gizmo.end_drag();
+    assert_close(cube.get_pos(), Vector3::new(0.0, 0.0, 0.0));
+    assert!(!gizmo.is_dragging());
+}
+
+#[test]
+fn rotate_turns_the_shape_with_the_ring() {
+    let (camera, projection) = camera();
+    let view = Viewport {
+        camera: &camera,
+        projection: &projection,
+        size: SIZE,
+    };
+    let mut cube = Cube::default();
+    let mut gizmo = Gizmo::default();
+    gizmo.mode = GizmoMode::Rotate;
+    gizmo.space = GizmoSpace::Local;
+    gizmo.snapping.enabled = true;
+    gizmo.snapping.rotate = Deg(15.0).into();
+
+    // grab the ring around x, which faces the camera, and drag it 80
+    // degrees from +y towards +z
+    let radius = GIZMO_SIZE * view.pixel_size(cube.get_pos());
+    let on_ring = |angle: Deg<f32>| {
+        let angle = Rad::from(angle).0;
+        view.project(Vector3::new(0.0, angle.cos(), angle.sin()) * radius)
+            .unwrap()
+    };
+    assert!(gizmo.begin_drag(&view, &cube, on_ring(Deg(45.0))));
+    let end = on_ring(Deg(125.0));
+    gizmo.drag(&view, &mut cube, end);
+    gizmo.end_drag();
+
+    // snapped to 75 degrees, the cube's y axis follows the ring
+    let angle = Rad::<f32>::from(Deg(75.0)).0;
+    let axes = gizmo.axes(&cube);
+    assert_close(axes[0], Vector3::unit_x());
+    assert_close(axes[1], Vector3::new(0.0, angle.cos(), angle.sin()));
+}
+
+#[test]
+fn scale_changes_bounds_and_radius() {
+    let (camera, projection) = camera();
+    let view = Viewport {
+        camera: &camera,
+        projection: &projection,
+        size: SIZE,
+    };
+    let mut gizmo = Gizmo::default();
+    gizmo.mode = GizmoMode::Scale;
+    let pixel = view.pixel_size(Vector3::new(0.0, 0.0, 0.0));
+
+    let mut cube = Cube::default();
+    let start = along(&view, &gizmo, &cube, 2, GIZMO_SIZE);
+    assert!(gizmo.begin_drag(&view, &cube, start));
+    let end = along(&view, &gizmo, &cube, 2, GIZMO_SIZE + 0.5 / pixel);
+    gizmo.drag(&view, &mut cube, end);
+    gizmo.end_drag();
+    assert_close(cube.bounds(), Vector3::new(1.0, 1.0, 1.5));
+
+    let mut sphere = Sphere::default();
+    let radius = sphere.radius();
+    let start = along(&view, &gizmo, &sphere, 1, GIZMO_SIZE);
+    assert!(gizmo.begin_drag(&view, &sphere, start));
+    let end = along(&view, &gizmo, &sphere, 1, GIZMO_SIZE - 0.25 / pixel);
+    gizmo.drag(&view, &mut sphere, end);
+    gizmo.end_drag();
+    assert!((sphere.radius() - (radius - 0.25)).abs() < 1e-3);
+
+    // spheres have no rotation rings
+    gizmo.mode = GizmoMode::Rotate;
+    assert!(!gizmo.begin_drag(&view, &sphere, start));
+}
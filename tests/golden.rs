@@ -0,0 +1,348 @@
+// Renders reference scenes and compares them against the PNGs in
+// tests/golden. The CPU marcher is always compared, and the real render
+// pipeline on a software adapter too when there is one. Run with
+// UPDATE_GOLDEN=1 to write new references from the render pipeline after an
+// intended change. Failed comparisons save the render and a diff image under
+// the target directory.
+
+use std::path::{Path, PathBuf};
+
+use cgmath::{InnerSpace, Point3, Quaternion, Rad, Rotation3, Vector3};
+use image::{Rgba, RgbaImage};
+use ray_marching::util::adapter::{AdapterOptions, GpuError};
+use ray_marching::util::camera::{Camera, Projection, ProjectionMode};
+use ray_marching::util::cpu_march;
+use ray_marching::util::shapes::{Shape, ShapeManager};
+use ray_marching::{Quality, ShaderParams};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 48;
+
+// CIE76 color difference a pixel may be off by, a little above the
+// smallest difference people notice side by side
+const MAX_DELTA_E: f32 = 3.0;
+// Share of pixels allowed beyond MAX_DELTA_E, for silhouette edges that
+// flip between shapes on small numeric changes
+const MAX_BAD_PIXELS: f32 = 0.005;
+
+struct Scene {
+    shapes: ShapeManager,
+    camera: Camera,
+    projection: Projection,
+}
+
+fn looking_at(pos: (f32, f32, f32), target: (f32, f32, f32)) -> Camera {
+    let dir = (Point3::from(target) - Point3::from(pos)).normalize();
+    Camera::new(pos, Rad(dir.z.atan2(dir.x)), Rad(dir.y.asin()))
+}
+
+fn perspective() -> Projection {
+    Projection::new(WIDTH, HEIGHT, cgmath::Deg(45.0), 0.1, 100.0)
+}
+
+fn params(projection: &Projection) -> ShaderParams {
+    let mut params = ShaderParams::new(WIDTH, HEIGHT, projection);
+    params.set_quality(Quality::High);
+    params.set_hud(false);
+    params
+}
+
+// None if there is no adapter to render on, leaving only the CPU comparison
+fn render(scene: &Scene) -> Option<RgbaImage> {
+    let options = AdapterOptions {
+        software: true,
+        ..Default::default()
+    };
+    let rendered = pollster::block_on(ray_marching::render_offscreen(
+        &options,
+        &scene.shapes,
+        &scene.camera,
+        &scene.projection,
+        &params(&scene.projection),
+    ));
+    match rendered {
+        Ok(rgba) => Some(RgbaImage::from_raw(WIDTH, HEIGHT, rgba).unwrap()),
+        Err(e) if e.is::<GpuError>() => {
+            eprintln!("no adapter to render on, only checking the CPU render: {}", e);
+            None
+        }
+        Err(e) => panic!("could not render: {}", e),
+    }
+}
+
+fn render_cpu(scene: &Scene) -> RgbaImage {
+    let params = params(&scene.projection);
+    let pixels = cpu_march::render(
+        &scene.shapes,
+        &scene.camera,
+        &scene.projection,
+        &params,
+        WIDTH,
+        HEIGHT,
+    );
+    RgbaImage::from_raw(WIDTH, HEIGHT, cpu_march::to_rgba8(&pixels)).unwrap()
+}
+
+//#region comparison
+fn srgb_to_lab(pixel: &Rgba<u8>) -> [f32; 3] {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(pixel[0]), linear(pixel[1]), linear(pixel[2]));
+    // XYZ relative to the D65 white point
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.9505;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.089;
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn delta_e(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let (a, b) = (srgb_to_lab(a), srgb_to_lab(b));
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+struct Comparison {
+    bad_pixels: u32,
+    max_delta: f32,
+    // the reference faded to gray with pixels beyond the tolerance in red
+    diff: RgbaImage,
+}
+
+impl Comparison {
+    fn passed(&self) -> bool {
+        self.bad_pixels as f32 <= MAX_BAD_PIXELS * (self.diff.width() * self.diff.height()) as f32
+    }
+}
+
+fn compare(expected: &RgbaImage, actual: &RgbaImage) -> Comparison {
+    assert_eq!(
+        expected.dimensions(),
+        actual.dimensions(),
+        "image sizes differ"
+    );
+    let mut bad_pixels = 0;
+    let mut max_delta = 0.0f32;
+    let diff = RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let (e, a) = (expected.get_pixel(x, y), actual.get_pixel(x, y));
+        let delta = delta_e(e, a);
+        max_delta = max_delta.max(delta);
+        if delta > MAX_DELTA_E {
+            bad_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let gray = (64.0 + srgb_to_lab(e)[0] * 0.64) as u8;
+            Rgba([gray, gray, gray, 255])
+        }
+    });
+    Comparison {
+        bad_pixels,
+        max_delta,
+        diff,
+    }
+}
+
+fn check(name: &str, scene: &Scene) {
+    let reference = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.png", name));
+    let gpu = render(scene);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        gpu.expect("updating the references needs an adapter").save(&reference).unwrap();
+        return;
+    }
+    let expected = match image::open(&reference) {
+        Ok(img) => img.to_rgba8(),
+        Err(e) => panic!(
+            "could not load {}: {}, run with UPDATE_GOLDEN=1 to create it",
+            reference.display(),
+            e
+        ),
+    };
+
+    // the CPU marcher backs the terminal renderer, picking and headless
+    // renders without a GPU, so it has to match the references too
+    check_against(name, "cpu", &expected, &render_cpu(scene));
+    if let Some(gpu) = gpu {
+        check_against(name, "gpu", &expected, &gpu);
+    }
+}
+
+fn check_against(name: &str, renderer: &str, expected: &RgbaImage, actual: &RgbaImage) {
+    let comparison = compare(expected, actual);
+    if !comparison.passed() {
+        let out = diff_dir();
+        std::fs::create_dir_all(&out).unwrap();
+        let actual_path = out.join(format!("{}_{}.png", name, renderer));
+        let diff_path = out.join(format!("{}_{}_diff.png", name, renderer));
+        actual.save(&actual_path).unwrap();
+        comparison.diff.save(&diff_path).unwrap();
+        panic!(
+            "the {} render of {} differs from its reference in {} pixels (max delta E {:.1}), see {} and {}",
+            renderer,
+            name,
+            comparison.bad_pixels,
+            comparison.max_delta,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+fn diff_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+//#endregion
+
+//#region scenes
+fn spheres_scene() -> Scene {
+    let mut shapes = ShapeManager::new();
+    shapes.new_sphere((0.0, 0.0, 0.0).into(), 1.0, (1.0, 0.0, 0.0).into());
+    shapes.new_sphere((1.0, 0.5, 2.5).into(), 0.6, (0.0, 1.0, 0.0).into());
+    shapes.new_sphere((3.0, -1.0, -2.5).into(), 1.5, (0.2, 0.4, 1.0).into());
+    Scene {
+        shapes,
+        camera: looking_at((-8.0, 2.0, 0.0), (0.0, 0.0, 0.0)),
+        projection: perspective(),
+    }
+}
+
+fn cubes_scene() -> Scene {
+    let mut shapes = ShapeManager::new();
+    shapes.new_cube(
+        (0.0, 0.0, -1.5).into(),
+        (1.0, 1.0, 1.0).into(),
+        (1.0, 0.8, 0.2).into(),
+    );
+    let cube = shapes.new_cube(
+        (0.0, 0.0, 1.5).into(),
+        (0.5, 1.5, 0.8).into(),
+        (0.6, 0.2, 0.8).into(),
+    );
+    cube.set_rotation(Quaternion::from_axis_angle(Vector3::unit_y(), Rad(0.6)));
+    Scene {
+        shapes,
+        camera: looking_at((-7.0, 3.0, -3.0), (0.0, 0.0, 0.0)),
+        projection: perspective(),
+    }
+}
+
+// Overlapping shapes, the scene is the union of everything in it
+fn csg_union_scene() -> Scene {
+    let mut shapes = ShapeManager::new();
+    shapes.new_cube(
+        (0.0, 0.0, 0.0).into(),
+        (1.0, 1.0, 1.0).into(),
+        (0.2, 0.8, 0.8).into(),
+    );
+    shapes.new_sphere((0.0, 0.8, 0.8).into(), 0.9, (0.9, 0.3, 0.1).into());
+    Scene {
+        shapes,
+        camera: looking_at((-5.0, 2.5, 2.0), (0.0, 0.3, 0.3)),
+        projection: perspective(),
+    }
+}
+
+// Seen from the light, with the specular highlight in view
+fn lit_side_scene() -> Scene {
+    let mut shapes = ShapeManager::new();
+    shapes.new_sphere((0.0, 0.0, 0.0).into(), 1.5, (0.8, 0.8, 0.8).into());
+    Scene {
+        shapes,
+        camera: looking_at((8.0, 8.0, 6.0), (0.0, 0.0, 0.0)),
+        projection: perspective(),
+    }
+}
+
+// Seen from opposite the light, only ambient light and the terminator
+fn dark_side_scene() -> Scene {
+    let mut shapes = ShapeManager::new();
+    shapes.new_sphere((0.0, 0.0, 0.0).into(), 1.5, (0.8, 0.8, 0.8).into());
+    Scene {
+        shapes,
+        camera: looking_at((-6.0, -2.0, -5.0), (0.0, 0.0, 0.0)),
+        projection: perspective(),
+    }
+}
+
+fn orthographic_scene() -> Scene {
+    let mut shapes = ShapeManager::new();
+    shapes.new_cube(
+        (0.0, 0.0, 0.0).into(),
+        (1.0, 1.0, 1.0).into(),
+        (0.9, 0.9, 0.9).into(),
+    );
+    shapes.new_sphere((-1.0, 0.0, 2.0).into(), 1.0, (1.0, 0.2, 0.2).into());
+    let mut projection = perspective();
+    projection.set_mode(ProjectionMode::Orthographic);
+    projection.set_ortho_extent(3.0);
+    Scene {
+        shapes,
+        camera: looking_at((-8.0, 6.0, -6.0), (0.0, 0.0, 0.0)),
+        projection,
+    }
+}
+//#endregion
+
+#[test]
+fn spheres() {
+    check("spheres", &spheres_scene());
+}
+
+#[test]
+fn cubes() {
+    check("cubes", &cubes_scene());
+}
+
+#[test]
+fn csg_union() {
+    check("csg_union", &csg_union_scene());
+}
+
+#[test]
+fn lit_side() {
+    check("lit_side", &lit_side_scene());
+}
+
+#[test]
+fn dark_side() {
+    check("dark_side", &dark_side_scene());
+}
+
+#[test]
+fn orthographic() {
+    check("orthographic", &orthographic_scene());
+}
+
+#[test]
+fn comparison_tolerates_small_differences() {
+    let expected = RgbaImage::from_pixel(10, 10, Rgba([100, 150, 200, 255]));
+    let mut actual = expected.clone();
+    assert!(compare(&expected, &actual).passed());
+
+    // off by a step everywhere
+    actual.pixels_mut().for_each(|p| p[0] += 1);
+    let comparison = compare(&expected, &actual);
+    assert!(comparison.passed());
+    assert_eq!(comparison.bad_pixels, 0);
+
+    // one visibly wrong pixel out of 100 is too many
+    actual.put_pixel(3, 4, Rgba([255, 0, 0, 255]));
+    let comparison = compare(&expected, &actual);
+    assert!(!comparison.passed());
+    assert_eq!(comparison.bad_pixels, 1);
+    assert_eq!(*comparison.diff.get_pixel(3, 4), Rgba([255, 0, 0, 255]));
+    assert_ne!(*comparison.diff.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+}
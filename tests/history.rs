@@ -0,0 +1,129 @@
+use cgmath::Vector3;
+use ray_marching::util::history::{Command, History, ListChange, ShapeState};
+use ray_marching::util::shapes::{Cube, Flag, Shape, ShapeManager, Sphere};
+
+fn positions(shapes: &ShapeManager) -> Vec<f32> {
+    shapes.iter_shapes().map(|shape| shape.get_pos().x).collect()
+}
+
+fn scene() -> ShapeManager {
+    let mut shapes = ShapeManager::new();
+    shapes.new_sphere((0.0, 0.0, 0.0).into(), 1.0, (1.0, 0.0, 0.0).into());
+    shapes.new_cube((1.0, 0.0, 0.0).into(), (1.0, 1.0, 1.0).into(), (0.0, 1.0, 0.0).into());
+    shapes.new_cube((2.0, 0.0, 0.0).into(), (1.0, 1.0, 1.0).into(), (0.0, 0.0, 1.0).into());
+    shapes
+}
+
+#[test]
+fn adding_and_removing_can_be_undone() {
+    let mut shapes = scene();
+    let mut history = History::default();
+    assert!(!history.can_undo());
+
+    let added = history.execute(
+        &mut shapes,
+        Command::AddCube {
+            pos: (3.0, 0.0, 0.0).into(),
+            bounds: (1.0, 1.0, 1.0).into(),
+            color: (1.0, 1.0, 1.0).into(),
+        },
+    );
+    assert_eq!(added, Some(ListChange::Inserted { index: 3, cube: Some(2) }));
+    let removed = history.execute(&mut shapes, Command::Remove(1));
+    assert_eq!(removed, Some(ListChange::Removed { index: 1, cube: Some(0) }));
+    assert_eq!(positions(&shapes), vec![0.0, 2.0, 3.0]);
+    assert_eq!(history.execute(&mut shapes, Command::Remove(7)), None);
+
+    // the removed cube comes back in its place with its number
+    let changes = history.undo(&mut shapes).unwrap();
+    assert_eq!(changes, vec![ListChange::Inserted { index: 1, cube: Some(0) }]);
+    assert_eq!(positions(&shapes), vec![0.0, 1.0, 2.0, 3.0]);
+    assert_eq!(shapes.get_cube(0).unwrap().get_pos().x, 1.0);
+    assert_eq!(shapes.get_cube(2).unwrap().get_pos().x, 3.0);
+
+    history.undo(&mut shapes).unwrap();
+    assert_eq!(positions(&shapes), vec![0.0, 1.0, 2.0]);
+    assert!(history.undo(&mut shapes).is_none());
+
+    history.redo(&mut shapes).unwrap();
+    history.redo(&mut shapes).unwrap();
+    assert_eq!(positions(&shapes), vec![0.0, 2.0, 3.0]);
+    assert!(!history.can_redo());
+}
+
+#[test]
+fn edits_restore_every_property() {
+    let mut shapes = scene();
+    let mut history = History::default();
+    let before = ShapeState::of(shapes.get_shape(1).unwrap());
+
+    history.edit(&mut shapes, 1, |shape| {
+        shape.translate((0.0, 2.0, 0.0).into());
+        shape.set_color((0.5, 0.5, 0.5).into());
+        shape.set_flag(Flag::Enabled, false);
+        shape.as_any_mut().downcast_mut::<Cube>().unwrap().set_bounds((2.0, 1.0, 1.0).into());
+    });
+    let after = ShapeState::of(shapes.get_shape(1).unwrap());
+    assert_ne!(before, after);
+
+    history.undo(&mut shapes).unwrap();
+    assert_eq!(ShapeState::of(shapes.get_shape(1).unwrap()), before);
+    history.redo(&mut shapes).unwrap();
+    assert_eq!(ShapeState::of(shapes.get_shape(1).unwrap()), after);
+
+    // edits that change nothing are not recorded
+    history.undo(&mut shapes).unwrap();
+    assert_eq!(history.edit(&mut shapes, 0, |shape| shape.get_pos().x), Some(0.0));
+    assert!(!history.can_undo());
+    assert!(history.can_redo());
+    assert_eq!(history.edit(&mut shapes, 9, |_| ()), None);
+}
+
+#[test]
+fn new_edits_clear_redo() {
+    let mut shapes = scene();
+    let mut history = History::default();
+    history.edit(&mut shapes, 0, |shape| shape.translate((1.0, 0.0, 0.0).into()));
+    history.undo(&mut shapes).unwrap();
+    assert!(history.can_redo());
+    history.edit(&mut shapes, 0, |shape| shape.translate((0.0, 1.0, 0.0).into()));
+    assert!(!history.can_redo());
+    assert!(history.redo(&mut shapes).is_none());
+}
+
+#[test]
+fn groups_undo_together() {
+    let mut shapes = scene();
+    let mut history = History::default();
+
+    // a drag moves a shape a little every frame
+    history.begin_group();
+    for _ in 0..10 {
+        history.edit(&mut shapes, 2, |shape| shape.translate((0.0, 0.1, 0.0).into()));
+    }
+    history.end_group();
+    history.begin_group();
+    history.end_group();
+    history.edit(&mut shapes, 0, |shape| {
+        let sphere = shape.as_any_mut().downcast_mut::<Sphere>().unwrap();
+        sphere.set_radius(2.0)
+    });
+
+    history.undo(&mut shapes).unwrap();
+    assert_eq!(shapes.get_sphere(0).unwrap().radius(), 1.0);
+    assert!((shapes.get_shape(2).unwrap().get_pos().y - 1.0).abs() < 1e-5);
+    history.undo(&mut shapes).unwrap();
+    assert_eq!(shapes.get_shape(2).unwrap().get_pos(), Vector3::new(2.0, 0.0, 0.0));
+    assert!(!history.can_undo());
+}
+
+#[test]
+fn list_changes_shift_indices() {
+    let inserted = ListChange::Inserted { index: 2, cube: None };
+    assert_eq!(inserted.shift(1), Some(1));
+    assert_eq!(inserted.shift(2), Some(3));
+    let removed = ListChange::Removed { index: 2, cube: None };
+    assert_eq!(removed.shift(1), Some(1));
+    assert_eq!(removed.shift(2), None);
+    assert_eq!(removed.shift(3), Some(2));
+}
@@ -0,0 +1,192 @@
+use image::{GrayAlphaImage, GrayImage, LumaA, Luma, Rgba, RgbaImage};
+use ray_marching::util::frame_stream::FrameStream;
+use ray_marching::util::image::{Frame, PixelFormat, Video};
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Saves frame 1 of a new sequence and loads it back at 2x2.
+fn load<F: FnOnce(&std::path::Path)>(name: &str, save: F) -> Video {
+    let dir = temp_dir(name);
+    save(&dir.join("f0001.png"));
+    let video = Video::load(dir.to_str().unwrap(), 2, 2);
+    std::fs::remove_dir_all(&dir).unwrap();
+    video
+}
+
+#[test]
+fn grayscale_frames_load() {
+    let video = load("video_l8", |path| {
+        GrayImage::from_fn(2, 2, |x, _| Luma([if x == 0 { 0 } else { 255 }]))
+            .save(path)
+            .unwrap()
+    });
+    assert_eq!(video.get_frame_count(), 1);
+    assert_eq!(video.wait_frame(0).format(), PixelFormat::L8);
+    assert_eq!(video.get_pixel_value(0, 0, 0), 0.0);
+    assert_eq!(video.get_pixel_value(0, 1, 1), 1.0);
+    assert_eq!(video.get_pixel_color(0, 1, 0), [1.0, 1.0, 1.0]);
+    assert_eq!(video.get_pixel_alpha(0, 1, 0), 1.0);
+
+    let video = load("video_la8", |path| {
+        GrayAlphaImage::from_pixel(2, 2, LumaA([255, 0])).save(path).unwrap()
+    });
+    assert_eq!(video.wait_frame(0).format(), PixelFormat::La8);
+    assert_eq!(video.get_pixel_value(0, 0, 0), 1.0);
+    assert_eq!(video.get_pixel_alpha(0, 0, 0), 0.0);
+}
+
+#[test]
+fn color_and_alpha_are_kept() {
+    let video = load("video_rgba8", |path| {
+        RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 51])).save(path).unwrap()
+    });
+    assert_eq!(video.wait_frame(0).format(), PixelFormat::Rgba8);
+    assert_eq!(video.get_pixel_color(0, 1, 1), [1.0, 0.0, 0.0]);
+    assert!((video.get_pixel_alpha(0, 1, 1) - 0.2).abs() < 1e-6);
+    // luminance is weighted, pure red is darker than pure green
+    assert!((video.get_pixel_value(0, 1, 1) - 0.2126).abs() < 1e-6);
+}
+
+#[test]
+fn frames_convert_between_formats() {
+    let frame = Frame::new(PixelFormat::Rgb8, 2, 1, vec![0, 255, 0, 10, 20, 30]);
+    assert_eq!(frame.rgba(0, 0), [0, 255, 0, 255]);
+    assert_eq!(frame.rgba(1, 0), [10, 20, 30, 255]);
+    assert!((frame.luminance_view()[0] - 0.7152).abs() < 1e-6);
+
+    let frame = Frame::new(PixelFormat::La8, 1, 1, vec![128, 64]);
+    assert_eq!(frame.rgba(0, 0), [128, 128, 128, 64]);
+}
+
+#[test]
+fn streamed_frames_match_loaded_ones() {
+    let dir = temp_dir("video_stream");
+    for n in 0..10u8 {
+        GrayImage::from_pixel(2, 2, Luma([n * 20]))
+            .save(dir.join(format!("f{:0>4}.png", n + 1)))
+            .unwrap();
+    }
+    let path = dir.to_str().unwrap();
+    let loaded = Video::load(path, 2, 2);
+    let paths = (1..=10).map(|n| dir.join(format!("f{:0>4}.png", n))).collect();
+    let stream = FrameStream::new(paths, 2, 2, 3);
+    let video = Video::stream(path, 2, 2, 3);
+
+    assert_eq!(video.get_frame_count(), 10);
+    assert!(video.is_streaming());
+    for index in [0, 7, 9, 2] {
+        assert_eq!(video.wait_frame(index), loaded.wait_frame(index));
+        assert_eq!(*stream.wait_frame(index), *loaded.wait_frame(index));
+        assert!(stream.cached() <= 3);
+    }
+    // frames past the end are never ready
+    assert!(video.get_frame(10).is_none());
+
+    drop((video, stream));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn streams_decode_ahead_in_the_direction_of_playback() {
+    let dir = temp_dir("video_stream_reverse");
+    let paths: Vec<_> = (0..10).map(|n| dir.join(format!("f{:0>4}.png", n))).collect();
+    for (n, path) in paths.iter().enumerate() {
+        GrayImage::from_pixel(2, 2, Luma([n as u8 * 20])).save(path).unwrap();
+    }
+    let stream = FrameStream::new(paths, 2, 2, 3);
+
+    let decoded = |frames: &[usize]| {
+        let start = std::time::Instant::now();
+        while start.elapsed().as_secs() < 5 {
+            if frames.iter().all(|i| stream.is_cached(*i)) {
+                return true;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        false
+    };
+
+    // playing backwards from frame 5, as PingPong does on the way back
+    stream.wait_frame(5);
+    stream.wait_frame(4);
+    assert!(decoded(&[3, 2]));
+
+    // looping from the last frame to the first still plays forwards
+    stream.wait_frame(9);
+    stream.wait_frame(0);
+    assert!(decoded(&[1, 2]));
+
+    drop(stream);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn gifs_keep_their_frame_delays() {
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, Frame as GifFrame};
+
+    let dir = temp_dir("video_gif");
+    let file = dir.join("clip.gif");
+    let mut encoder = GifEncoder::new(std::fs::File::create(&file).unwrap());
+    for (shade, ms) in [(0, 100), (255, 300), (128, 50)] {
+        let img = RgbaImage::from_pixel(4, 4, Rgba([shade, shade, shade, 255]));
+        let delay = Delay::from_numer_denom_ms(ms, 1);
+        encoder.encode_frame(GifFrame::from_parts(img, 0, 0, delay)).unwrap();
+    }
+    drop(encoder);
+
+    let video = Video::open(file.to_str().unwrap(), 2, 2).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(video.get_frame_count(), 3);
+    assert!((video.duration(30.0) - 0.45).abs() < 1e-5);
+    assert_eq!(video.frame_index_from_time(0.05, 30.0), 0);
+    assert_eq!(video.frame_index_from_time(0.35, 30.0), 1);
+    assert_eq!(video.frame_index_from_time(0.41, 30.0), 2);
+    assert_eq!(video.get_pixel_value(1, 1, 1), 1.0);
+}
+
+#[test]
+fn still_images_are_one_frame_videos() {
+    let dir = temp_dir("video_still");
+    let file = dir.join("still.png");
+    GrayImage::from_pixel(8, 8, Luma([255])).save(&file).unwrap();
+
+    let video = Video::open(file.to_str().unwrap(), 2, 2).unwrap();
+    assert_eq!(video.get_frame_count(), 1);
+    assert!(video.delays().is_none());
+    assert_eq!(video.get_pixel_value(0, 0, 0), 1.0);
+
+    assert!(Video::open(dir.join("missing").to_str().unwrap(), 2, 2).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sequences_use_numeric_order_and_patterns() {
+    let dir = temp_dir("video_numbering");
+    // unpadded numbers starting at 0, plus an unrelated image
+    for n in [0u8, 9, 10, 2] {
+        GrayImage::from_pixel(1, 1, Luma([n]))
+            .save(dir.join(format!("shot_{}.png", n)))
+            .unwrap();
+    }
+    GrayImage::from_pixel(1, 1, Luma([200])).save(dir.join("cover.png")).unwrap();
+
+    let shades = |video: &Video| {
+        (0..video.get_frame_count())
+            .map(|i| video.wait_frame(i).rgba(0, 0)[0])
+            .collect::<Vec<_>>()
+    };
+    let pattern = dir.join("shot_*.png");
+    assert_eq!(shades(&Video::load(pattern.to_str().unwrap(), 1, 1)), vec![0, 2, 9, 10]);
+    let pattern = dir.join("shot_?.png");
+    assert_eq!(shades(&Video::new(pattern.to_str().unwrap(), 1, 1)), vec![0, 2, 9]);
+    // whole directories include every image, unnumbered ones first
+    assert_eq!(shades(&Video::load(dir.to_str().unwrap(), 1, 1)), vec![200, 0, 2, 9, 10]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,125 @@
+use ray_marching::util::input::{Action, Binding, InputMap, Rebinding};
+use winit::event::{MouseButton, VirtualKeyCode};
+
+#[test]
+fn defaults_match_the_old_hard_coded_keys() {
+    let map = InputMap::default();
+    let actions = |key| map.actions(Binding::Key(key)).collect::<Vec<_>>();
+    assert_eq!(actions(VirtualKeyCode::W), vec![Action::MoveForward]);
+    assert_eq!(actions(VirtualKeyCode::Up), vec![Action::MoveForward]);
+    assert_eq!(actions(VirtualKeyCode::LShift), vec![Action::MoveDown]);
+    assert_eq!(actions(VirtualKeyCode::Q), vec![Action::PlayVideo]);
+    assert!(map.is_bound(Binding::Key(VirtualKeyCode::Escape), Action::Quit));
+    assert!(map.is_bound(Binding::Mouse(MouseButton::Left), Action::Look));
+}
+
+#[test]
+fn undo_and_redo_are_ctrl_chords() {
+    let map = InputMap::default();
+    let chord = |shift| Binding::Ctrl {
+        key: VirtualKeyCode::Z,
+        shift,
+    };
+    assert_eq!(map.actions(chord(false)).collect::<Vec<_>>(), vec![Action::Undo]);
+    assert_eq!(map.actions(chord(true)).collect::<Vec<_>>(), vec![Action::Redo]);
+    // Z alone still rolls
+    assert!(map.is_bound(Binding::Key(VirtualKeyCode::Z), Action::RollLeft));
+}
+
+#[test]
+fn rebinding_moves_the_binding() {
+    let mut map = InputMap::default();
+    // e.g. ZQSD on AZERTY keyboards
+    map.rebind(Action::MoveForward, Binding::Key(VirtualKeyCode::Z));
+    assert_eq!(map.bindings(Action::MoveForward), &[Binding::Key(VirtualKeyCode::Z)]);
+    assert!(map.bindings(Action::RollLeft).is_empty());
+    assert_eq!(map.actions(Binding::Key(VirtualKeyCode::W)).count(), 0);
+}
+
+#[test]
+fn interactive_rebinding() {
+    let mut map = InputMap::default();
+    let mut rebinding = Rebinding::SelectAction;
+    assert!(rebinding.process(Binding::Key(VirtualKeyCode::Q), &mut map));
+    assert_eq!(rebinding, Rebinding::NewBinding(Action::PlayVideo));
+    assert!(rebinding.process(Binding::Key(VirtualKeyCode::B), &mut map));
+    assert_eq!(rebinding, Rebinding::Idle);
+    assert!(map.is_bound(Binding::Key(VirtualKeyCode::B), Action::PlayVideo));
+    assert!(!rebinding.process(Binding::Key(VirtualKeyCode::B), &mut map));
+}
+
+#[test]
+fn rebinding_to_a_ctrl_chord() {
+    let mut map = InputMap::default();
+    let mut rebinding = Rebinding::NewBinding(Action::Undo);
+    // Ctrl arrives on its own first, then as part of the chord
+    assert!(rebinding.process(Binding::Key(VirtualKeyCode::LControl), &mut map));
+    assert_eq!(rebinding, Rebinding::ModifierHeld(Action::Undo, VirtualKeyCode::LControl));
+    let chord = Binding::Ctrl {
+        key: VirtualKeyCode::Y,
+        shift: false,
+    };
+    assert!(rebinding.process(chord, &mut map));
+    assert_eq!(rebinding, Rebinding::Idle);
+    assert_eq!(map.bindings(Action::Undo), &[chord]);
+
+    // a modifier released on its own is bound by itself
+    let mut rebinding = Rebinding::NewBinding(Action::MoveUp);
+    assert!(rebinding.process(Binding::Key(VirtualKeyCode::RShift), &mut map));
+    assert!(!rebinding.release(Binding::Key(VirtualKeyCode::LShift), &mut map));
+    assert!(rebinding.release(Binding::Key(VirtualKeyCode::RShift), &mut map));
+    assert_eq!(rebinding, Rebinding::Idle);
+    assert!(map.is_bound(Binding::Key(VirtualKeyCode::RShift), Action::MoveUp));
+}
+
+#[test]
+fn rebinding_a_shared_binding_asks_which_action() {
+    let mut map = InputMap::default();
+    let left = Binding::Mouse(MouseButton::Left);
+    let mut rebinding = Rebinding::SelectAction;
+    assert!(rebinding.process(left, &mut map));
+    assert_eq!(rebinding, Rebinding::ChooseAction(left));
+    // Look comes first, Select second
+    assert!(rebinding.process(Binding::Key(VirtualKeyCode::Key2), &mut map));
+    assert_eq!(rebinding, Rebinding::NewBinding(Action::Select));
+    assert!(rebinding.process(Binding::Mouse(MouseButton::Right), &mut map));
+    assert!(map.is_bound(Binding::Mouse(MouseButton::Right), Action::Select));
+    assert!(map.is_bound(left, Action::Look));
+
+    // anything but the number of an action cancels
+    let mut rebinding = Rebinding::ChooseAction(left);
+    map.rebind(Action::Select, left);
+    map.add_binding(Action::Look, left);
+    assert!(rebinding.process(Binding::Key(VirtualKeyCode::Key3), &mut map));
+    assert_eq!(rebinding, Rebinding::Idle);
+}
+
+#[test]
+fn partial_files_keep_the_other_defaults() {
+    let file = std::env::temp_dir().join(format!("bindings_{}.ron", std::process::id()));
+    std::fs::write(&file, "(bindings: { MoveForward: [Key(Comma)] })").unwrap();
+    let map = InputMap::load(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+
+    assert_eq!(map.bindings(Action::MoveForward), &[Binding::Key(VirtualKeyCode::Comma)]);
+    assert!(map.is_bound(Binding::Key(VirtualKeyCode::S), Action::MoveBackward));
+}
+
+#[test]
+fn loaded_bindings_are_taken_from_the_defaults() {
+    let file = std::env::temp_dir().join(format!("bindings_conflict_{}.ron", std::process::id()));
+    std::fs::write(
+        &file,
+        "(bindings: { Quit: [Key(F)], PlayVideo: [Key(F4)], StopVideo: [Key(F4)] })",
+    )
+    .unwrap();
+    let map = InputMap::load(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+
+    // F no longer frames the scene as well as quitting
+    let f: Vec<Action> = map.actions(Binding::Key(VirtualKeyCode::F)).collect();
+    assert_eq!(f, vec![Action::Quit]);
+    assert!(map.bindings(Action::FrameScene).is_empty());
+    // bindings the file shares itself are kept
+    assert_eq!(map.actions(Binding::Key(VirtualKeyCode::F4)).count(), 2);
+}
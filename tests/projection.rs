@@ -0,0 +1,58 @@
+use cgmath::Deg;
+use ray_marching::util::camera::{Camera, Projection, ProjectionMode};
+use ray_marching::util::shapes::ShapeManager;
+
+fn assert_box_eq(actual: [f32; 4], expected: [f32; 4]) {
+    for (a, e) in actual.iter().zip(expected.iter()) {
+        assert!((a - e).abs() < 1e-2, "{:?} != {:?}", actual, expected);
+    }
+}
+
+// The shader offsets orthographic ray origins by
+// (pixel - size / 2) / (height / 2) * extent, so a unit sphere seen with an
+// extent of 5 on an 800x600 screen covers 60 pixels either side of the centre.
+#[test]
+fn orthographic_screen_box_matches_shader_mapping() {
+    let camera = Camera::new((-10.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+    let mut projection = Projection::new(800, 600, Deg(45.0), 0.1, 100.0);
+    projection.set_mode(ProjectionMode::Orthographic);
+    projection.set_ortho_extent(5.0);
+
+    let mut shapes = ShapeManager::new();
+    shapes.new_sphere((0.0, 0.0, 0.0).into(), 1.0, (1.0, 1.0, 1.0).into());
+    let screen_box = shapes.get_shape(0).unwrap().get_screen_bounding_box(
+        camera.calc_matrix(),
+        projection.calc_matrix(),
+        (800, 600),
+    );
+    assert_box_eq(screen_box, [340.0, 240.0, 460.0, 360.0]);
+
+    // Orthographic boxes do not depend on distance
+    shapes
+        .get_shape_mut(0)
+        .unwrap()
+        .translate((20.0, 0.0, 0.0).into());
+    let far_box = shapes.get_shape(0).unwrap().get_screen_bounding_box(
+        camera.calc_matrix(),
+        projection.calc_matrix(),
+        (800, 600),
+    );
+    assert_box_eq(far_box, screen_box);
+}
+
+#[test]
+fn perspective_screen_box_shrinks_with_distance() {
+    let camera = Camera::new((-10.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+    let projection = Projection::new(800, 600, Deg(45.0), 0.1, 100.0);
+
+    let mut shapes = ShapeManager::new();
+    shapes.new_sphere((0.0, 0.0, 0.0).into(), 1.0, (1.0, 1.0, 1.0).into());
+    shapes.new_sphere((20.0, 0.0, 0.0).into(), 1.0, (1.0, 1.0, 1.0).into());
+    let boxes: Vec<_> = shapes
+        .iter_shapes()
+        .map(|s| {
+            s.get_screen_bounding_box(camera.calc_matrix(), projection.calc_matrix(), (800, 600))
+        })
+        .collect();
+    assert!(boxes[1][2] - boxes[1][0] < boxes[0][2] - boxes[0][0]);
+}
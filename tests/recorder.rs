@@ -0,0 +1,67 @@
+use ray_marching::util::recorder::{rgba_to_yuv444, RecordFormat, Recorder};
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}_{}", name, std::process::id()))
+}
+
+// 2x1 frames, the left pixel red and the right one shaded `n`
+fn frame(n: u8) -> Vec<u8> {
+    vec![255, 0, 0, 255, n, n, n, 255]
+}
+
+#[test]
+fn png_sequences_are_numbered() {
+    let dir = temp_path("record_png");
+    let mut recorder = Recorder::new(&dir, RecordFormat::PngSequence, 2, 1, 30).unwrap();
+    for n in 0..3 {
+        recorder.write_frame(&frame(n * 100)).unwrap();
+    }
+    assert!(recorder.write_frame(&[0; 4]).is_err());
+    assert_eq!(recorder.finish().unwrap(), 3);
+
+    let img = image::open(dir.join("frame_00002.png")).unwrap().to_rgba8();
+    assert_eq!(img.into_raw(), frame(100));
+    assert!(dir.join("frame_00003.png").is_file());
+    assert!(!dir.join("frame_00004.png").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn y4m_streams_have_a_header_and_planar_frames() {
+    let file = temp_path("record.y4m");
+    let mut recorder = Recorder::new(&file, RecordFormat::Y4m, 2, 1, 25).unwrap();
+    recorder.write_frame(&frame(255)).unwrap();
+    recorder.write_frame(&frame(0)).unwrap();
+    recorder.finish().unwrap();
+
+    let data = std::fs::read(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+    let header = b"YUV4MPEG2 W2 H1 F25:1 Ip A1:1 C444 XCOLORRANGE=LIMITED\n";
+    assert!(data.starts_with(header));
+    let frames = &data[header.len()..];
+    assert_eq!(frames.len(), 2 * (6 + 2 * 3));
+    assert!(frames.starts_with(b"FRAME\n"));
+
+    // limited range: white is 235, black is 16 and neutral chroma is 128
+    assert_eq!(&frames[6..12], &[81, 235, 90, 128, 240, 128]);
+    assert_eq!(rgba_to_yuv444(&frame(0))[1], 16);
+}
+
+#[test]
+fn gifs_decode_back() {
+    use image::AnimationDecoder;
+
+    let file = temp_path("record.gif");
+    let mut recorder = Recorder::new(&file, RecordFormat::Gif, 2, 1, 20).unwrap();
+    for n in 0..4 {
+        recorder.write_frame(&frame(n * 60)).unwrap();
+    }
+    recorder.finish().unwrap();
+
+    let decoder = image::codecs::gif::GifDecoder::new(std::fs::File::open(&file).unwrap()).unwrap();
+    let frames = decoder.into_frames().collect_frames().unwrap();
+    std::fs::remove_file(&file).unwrap();
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[0].delay().numer_denom_ms(), (50, 1));
+}
@@ -0,0 +1,166 @@
+use ray_marching::util::camera::{Camera, Projection};
+use ray_marching::util::cli::RenderOptions;
+use ray_marching::util::scene::{CameraDesc, SceneFile, ShapeDesc, VideoWallDesc};
+use ray_marching::util::shapes::{Shape, ShapeManager};
+
+const SCENE: &str = r#"(
+    camera: Some((position: (-5.0, 1.0, 0.0), yaw: 10.0)),
+    shapes: [
+        Sphere(position: (0.0, 0.0, 0.0), radius: 1.0, color: (1.0, 0.0, 0.0)),
+        Cube(position: (0.0, 3.0, 0.0), half_size: (1.0, 0.5, 1.0), color: (0.0, 0.5, 1.0)),
+    ],
+)"#;
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("scene_test_{}_{}", std::process::id(), name))
+}
+
+#[test]
+fn load_and_build() {
+    let path = temp_path("load.ron");
+    std::fs::write(&path, SCENE).unwrap();
+    let scene = SceneFile::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(scene.validate().is_empty());
+
+    let camera = scene.camera.unwrap();
+    assert_eq!(camera.position, [-5.0, 1.0, 0.0]);
+    assert_eq!((camera.yaw, camera.pitch, camera.fovy), (10.0, 0.0, 45.0));
+    let mut cam = Camera::new((0.0, 0.0, 0.0), cgmath::Deg(0.0), cgmath::Deg(0.0));
+    let mut projection = Projection::new(10, 10, cgmath::Deg(60.0), 0.1, 100.0);
+    camera.apply(&mut cam, &mut projection);
+    assert!((cam.yaw().0 - 10f32.to_radians()).abs() < 1e-6);
+    assert!((projection.fovy().0 - 45f32.to_radians()).abs() < 1e-6);
+
+    let mut shapes = ShapeManager::new();
+    let wall = scene.build(&mut shapes).unwrap();
+    assert_eq!(wall.size(), (0, 0));
+    assert_eq!(shapes.shape_count(), 2);
+    assert_eq!(shapes.get_cube(0).unwrap().get_color(), (0.0, 0.5, 1.0).into());
+    assert_eq!(shapes.scene_sdf((0.0, 0.0, 0.0).into()).1, Some(0));
+}
+
+#[test]
+fn round_trip() {
+    let scene = SceneFile {
+        camera: Some(CameraDesc {
+            position: [1.0, 2.0, 3.0],
+            yaw: 0.0,
+            pitch: 5.0,
+            fovy: 60.0,
+        }),
+        shapes: vec![ShapeDesc::Cube {
+            position: [0.0, 0.0, 0.0],
+            half_size: [1.0, 1.0, 1.0],
+            color: [0.5, 0.5, 0.5],
+            rotation: [0.0, 45.0, 0.0],
+        }],
+        video_wall: None,
+    };
+    let path = temp_path("round_trip.ron");
+    scene.save(&path).unwrap();
+    assert_eq!(SceneFile::load(&path).unwrap(), scene);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn validation_finds_problems() {
+    let scene = SceneFile {
+        camera: None,
+        shapes: vec![
+            ShapeDesc::Sphere {
+                position: [0.0, 0.0, 0.0],
+                radius: -1.0,
+                color: [2.0, 0.0, 0.0],
+            },
+            ShapeDesc::Cube {
+                position: [0.0, 0.0, 0.0],
+                half_size: [1.0, 0.0, 1.0],
+                color: [0.0, 0.0, 0.0],
+                rotation: [0.0, 0.0, 0.0],
+            },
+        ],
+        video_wall: Some(VideoWallDesc {
+            path: "/does/not/exist/*.png".into(),
+            size: (0, 4),
+            fps: 30.0,
+            origin: [0.0, 0.0, 0.0],
+            spacing: 2.0,
+            color: [1.0, 1.0, 1.0],
+        }),
+    };
+    let problems = scene.validate();
+    assert_eq!(problems.len(), 5, "{:?}", problems);
+    assert!(problems[0].starts_with("shape 0 has a radius"));
+
+    let mut shapes = ShapeManager::new();
+    assert!(scene.build(&mut shapes).is_err());
+}
+
+#[test]
+fn non_finite_numbers_are_problems() {
+    let scene = SceneFile {
+        camera: Some(CameraDesc {
+            position: [f32::NAN, 0.0, 0.0],
+            yaw: 0.0,
+            pitch: f32::INFINITY,
+            fovy: 45.0,
+        }),
+        shapes: vec![
+            ShapeDesc::Sphere {
+                position: [0.0, 0.0, 0.0],
+                radius: f32::NAN,
+                color: [1.0, 0.0, 0.0],
+            },
+            ShapeDesc::Cube {
+                position: [0.0, f32::NEG_INFINITY, 0.0],
+                half_size: [1.0, f32::NAN, 1.0],
+                color: [0.0, 0.0, 0.0],
+                rotation: [f32::NAN, 0.0, 0.0],
+            },
+        ],
+        video_wall: None,
+    };
+    let problems = scene.validate();
+    assert_eq!(problems.len(), 6, "{:?}", problems);
+}
+
+#[test]
+fn invalid_scenes_are_not_rendered() {
+    let path = temp_path("invalid.ron");
+    let out = temp_path("invalid.png");
+    std::fs::write(&path, "(shapes: [Sphere(position: (0.0, 0.0, 0.0), radius: -1.0, color: (1.0, 0.0, 0.0))])")
+        .unwrap();
+    let options = RenderOptions {
+        scene: Some(path.clone()),
+        out: out.clone(),
+        size: (8, 8),
+        camera: None,
+        time: 0.0,
+        quality: ray_marching::Quality::Low,
+        adapter: Default::default(),
+    };
+    let error = ray_marching::render_to_file(&options).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    assert!(error.to_string().contains("radius"), "{}", error);
+    assert!(!out.exists());
+}
+
+#[test]
+fn syntax_errors_are_invalid_data() {
+    let path = temp_path("broken.ron");
+    std::fs::write(&path, "(shapes: [Sphere(radius: 1.0)])").unwrap();
+    let error = SceneFile::load(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn example_scenes_are_valid() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let scene = SceneFile::load(&path).unwrap();
+        assert!(scene.validate().is_empty(), "{}: {:?}", path.display(), scene.validate());
+    }
+}
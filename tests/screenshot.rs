@@ -0,0 +1,38 @@
+use ray_marching::util::screenshot::{downsample, save_png};
+
+#[test]
+fn downsample_averages_blocks() {
+    // 4x2 image: a white and a black 2x2 block
+    let mut rgba = Vec::new();
+    for _ in 0..2 {
+        rgba.extend_from_slice(&[255, 255, 255, 255, 255, 255, 255, 255]);
+        rgba.extend_from_slice(&[0, 0, 0, 255, 0, 0, 0, 255]);
+    }
+    let out = downsample(&rgba, 4, 2, 2);
+    assert_eq!(out, vec![255, 255, 255, 255, 0, 0, 0, 255]);
+
+    assert_eq!(downsample(&rgba, 4, 2, 1), rgba);
+}
+
+#[test]
+fn downsample_averages_in_linear_light() {
+    // half black, half white averages to 50% linear, not 50% sRGB
+    let rgba = [
+        0, 0, 0, 255, 255, 255, 255, 255, 0, 0, 0, 255, 255, 255, 255, 255,
+    ];
+    let out = downsample(&rgba, 2, 2, 2);
+    assert_eq!(out, vec![188, 188, 188, 255]);
+}
+
+#[test]
+fn save_png_round_trips() {
+    let dir = std::env::temp_dir().join(format!("screenshot_test_{}", std::process::id()));
+    let path = dir.join("shot.png");
+    let rgba = vec![10, 20, 30, 255, 40, 50, 60, 255];
+    save_png(&path, rgba.clone(), 2, 1).unwrap();
+    let loaded = image::open(&path).unwrap().to_rgba8();
+    assert_eq!(loaded.dimensions(), (2, 1));
+    assert_eq!(loaded.into_raw(), rgba);
+    assert!(save_png(dir.join("bad.png"), rgba, 3, 1).is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
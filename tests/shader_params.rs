@@ -0,0 +1,74 @@
+use cgmath::Deg;
+use ray_marching::util::camera::Projection;
+use ray_marching::{Quality, ShaderParams};
+
+fn params() -> ShaderParams {
+    let projection = Projection::new(16, 16, Deg(45.0), 0.1, 100.0);
+    ShaderParams::new(16, 16, &projection)
+}
+
+#[test]
+fn quality_presets_set_march_settings() {
+    let mut params = params();
+    // (max_steps, epsilon, relaxation, footprint_scale)
+    let presets = [
+        (Quality::Low, (32, 0.001, 1.6, 2.0)),
+        (Quality::Medium, (64, 0.0005, 1.4, 1.0)),
+        (Quality::High, (128, 0.0001, 1.2, 0.5)),
+        (Quality::Ultra, (256, 0.00005, 1.0, 0.0)),
+    ];
+    for (quality, expected) in presets {
+        params.set_quality(quality);
+        let settings = (
+            params.max_steps(),
+            params.epsilon(),
+            params.relaxation(),
+            params.footprint_scale(),
+        );
+        assert_eq!(settings, expected, "{:?}", quality);
+    }
+}
+
+#[test]
+fn new_params_use_medium_quality() {
+    let params = params();
+    assert_eq!(params.max_steps(), 64);
+    assert_eq!(params.epsilon(), 0.0005);
+}
+
+#[test]
+fn setters_keep_in_range_values() {
+    let mut params = params();
+    params.set_max_steps(200);
+    params.set_epsilon(0.002);
+    params.set_relaxation(1.5);
+    params.set_footprint_scale(3.0);
+    assert_eq!(params.max_steps(), 200);
+    assert_eq!(params.epsilon(), 0.002);
+    assert_eq!(params.relaxation(), 1.5);
+    assert_eq!(params.footprint_scale(), 3.0);
+}
+
+#[test]
+fn setters_clamp_out_of_range_values() {
+    let mut params = params();
+    params.set_max_steps(0);
+    assert_eq!(params.max_steps(), 1);
+
+    params.set_epsilon(0.0);
+    assert_eq!(params.epsilon(), f32::MIN_POSITIVE);
+    params.set_epsilon(-1.0);
+    assert_eq!(params.epsilon(), f32::MIN_POSITIVE);
+
+    // over-relaxation diverges outside [1, 2)
+    params.set_relaxation(0.5);
+    assert_eq!(params.relaxation(), 1.0);
+    params.set_relaxation(2.5);
+    assert_eq!(params.relaxation(), 1.99);
+
+    params.set_footprint_scale(-2.0);
+    assert_eq!(params.footprint_scale(), 0.0);
+
+    params.set_march_range(-1.0, -5.0);
+    assert_eq!(params.march_range(), (0.0, 0.0));
+}
@@ -0,0 +1,46 @@
+use cgmath::Vector3;
+use ray_marching::util::shapes::{Cube, Shape, ShapeManager, Sphere};
+
+fn positions(shapes: &ShapeManager) -> Vec<f32> {
+    shapes.iter_shapes().map(|shape| shape.get_pos().x).collect()
+}
+
+#[test]
+fn removing_renumbers_shapes_of_the_same_type() {
+    let mut shapes = ShapeManager::new();
+    shapes.new_sphere((0.0, 0.0, 0.0).into(), 1.0, (1.0, 0.0, 0.0).into());
+    shapes.new_cube((1.0, 0.0, 0.0).into(), (1.0, 1.0, 1.0).into(), (0.0, 1.0, 0.0).into());
+    shapes.new_sphere((2.0, 0.0, 0.0).into(), 2.0, (0.0, 0.0, 1.0).into());
+    shapes.new_cube((3.0, 0.0, 0.0).into(), (2.0, 2.0, 2.0).into(), (1.0, 1.0, 0.0).into());
+
+    let removed = shapes.remove_shape(0).unwrap();
+    assert!(removed.as_any().is::<Sphere>());
+    assert_eq!(positions(&shapes), vec![1.0, 2.0, 3.0]);
+    assert_eq!(shapes.sphere_count(), 1);
+
+    // the remaining sphere is now sphere 0, the cubes keep their numbers
+    let sphere = shapes.get_sphere(0).unwrap();
+    assert_eq!((sphere.get_pos().x, sphere.get_index()), (2.0, 0));
+    assert!(shapes.get_sphere(1).is_none());
+    assert_eq!(shapes.get_cube(1).unwrap().get_pos().x, 3.0);
+    assert_eq!(shapes.cube_shape_index(1), Some(2));
+
+    let removed = shapes.remove_shape(0).unwrap();
+    assert!(removed.as_any().is::<Cube>());
+    assert_eq!(shapes.get_cube(0).unwrap().get_pos().x, 3.0);
+    assert_eq!(shapes.get_cube(0).unwrap().get_index(), 0);
+    assert!(shapes.remove_shape(2).is_none());
+}
+
+#[test]
+fn added_shapes_follow_removed_ones() {
+    let mut shapes = ShapeManager::new();
+    shapes.new_sphere((0.0, 0.0, 0.0).into(), 1.0, (1.0, 1.0, 1.0).into());
+    shapes.new_sphere((1.0, 0.0, 0.0).into(), 1.0, (1.0, 1.0, 1.0).into());
+    shapes.remove_shape(0);
+    let sphere = shapes.new_sphere((2.0, 0.0, 0.0).into(), 1.0, (1.0, 1.0, 1.0).into());
+    assert_eq!(sphere.get_index(), 1);
+    assert_eq!(shapes.get_sphere(1).unwrap().get_pos(), Vector3::new(2.0, 0.0, 0.0));
+    // a vec4 per sphere
+    assert_eq!(shapes.serialize_spheres().len(), 2 * 16);
+}
@@ -0,0 +1,281 @@
+use cgmath::Vector3;
+use ray_marching::util::image::Video;
+use ray_marching::util::shapes::{Flag, Shape, ShapeManager};
+use ray_marching::util::video_wall::{PlaybackMode, VideoWall, WallMapping};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// Writes `frames` 4x3 frames where frame n is black except pixel (n, 0).
+fn write_frames(name: &str, frames: u32) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for n in 0..frames {
+        let img = image::RgbImage::from_fn(4, 3, |x, y| {
+            if x == n && y == 0 {
+                image::Rgb([255, 255, 255])
+            } else {
+                image::Rgb([0, 0, 0])
+            }
+        });
+        img.save(dir.join(format!("f{:0>4}.png", n + 1))).unwrap();
+    }
+    dir
+}
+
+fn half_size(shapes: &ShapeManager, wall: &VideoWall, x: u32, y: u32) -> f32 {
+    let cube = shapes.get_cube(wall.cube_at(x, y).unwrap()).unwrap();
+    let (min, max) = cube.get_world_bounding_box();
+    (max.x - min.x) / 2.0
+}
+
+fn color(shapes: &ShapeManager, wall: &VideoWall, x: u32, y: u32) -> Vector3<f32> {
+    shapes.get_cube(wall.cube_at(x, y).unwrap()).unwrap().get_color()
+}
+
+#[test]
+fn wall_owns_a_cube_per_pixel() {
+    let dir = write_frames("wall_grid", 2);
+    let mut shapes = ShapeManager::new();
+    let wall = VideoWall::new(
+        Video::load(dir.to_str().unwrap(), 4, 3),
+        10.0,
+        Vector3::new(1.0, 2.0, 3.0),
+        2.0,
+        (1.0, 1.0, 1.0).into(),
+        &mut shapes,
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(wall.cubes().len(), 12);
+    assert_eq!(shapes.shape_count(), 12);
+    assert_eq!(wall.duration(), 0.2);
+    let (min, max) = shapes
+        .get_cube(wall.cube_at(3, 2).unwrap())
+        .unwrap()
+        .get_world_bounding_box();
+    assert_eq!((min + max) / 2.0, Vector3::new(7.0, 6.0, 3.0));
+    assert!(wall.cube_at(4, 0).is_none());
+}
+
+#[test]
+fn playback_seek_and_loop() {
+    let dir = write_frames("wall_playback", 3);
+    let mut shapes = ShapeManager::new();
+    let mut wall = VideoWall::new(
+        Video::load(dir.to_str().unwrap(), 4, 3),
+        10.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        2.0,
+        (1.0, 1.0, 1.0).into(),
+        &mut shapes,
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // paused walls do not advance
+    wall.update(Duration::from_millis(150), &mut shapes);
+    assert_eq!(wall.time(), 0.0);
+
+    wall.play();
+    wall.update(Duration::from_millis(150), &mut shapes);
+    assert_eq!(half_size(&shapes, &wall, 1, 0), 1.0);
+    assert_eq!(half_size(&shapes, &wall, 0, 0), 0.0);
+
+    wall.seek(0.0);
+    wall.apply_frame(&mut shapes);
+    assert_eq!(half_size(&shapes, &wall, 0, 0), 1.0);
+
+    // by default playback holds the last frame
+    wall.update(Duration::from_secs(1), &mut shapes);
+    assert!(!wall.is_playing());
+    assert_eq!(half_size(&shapes, &wall, 2, 0), 1.0);
+
+    wall.set_playback_mode(PlaybackMode::Loop);
+    wall.seek(0.25);
+    wall.play();
+    wall.update(Duration::from_millis(100), &mut shapes);
+    assert!(wall.is_playing());
+    assert!(wall.time() < 0.1);
+
+    wall.stop(&mut shapes);
+    assert_eq!(wall.time(), 0.0);
+    assert_eq!(half_size(&shapes, &wall, 1, 0), 1.0);
+    assert_eq!(half_size(&shapes, &wall, 3, 2), 1.0);
+}
+
+#[test]
+fn mappings_drive_height_color_and_visibility() {
+    let dir = write_frames("wall_mappings", 2);
+    let mut shapes = ShapeManager::new();
+    let mut wall = VideoWall::new(
+        Video::load(dir.to_str().unwrap(), 4, 3),
+        10.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        2.0,
+        (0.5, 0.5, 0.5).into(),
+        &mut shapes,
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    wall.set_mappings(vec![
+        WallMapping::Height { height: 6.0 },
+        WallMapping::Color,
+        WallMapping::Visibility { threshold: 0.5 },
+    ]);
+    wall.apply_frame(&mut shapes);
+
+    // the lit pixel is a 6 unit bar standing on the back of the wall
+    let lit = shapes.get_cube(wall.cube_at(0, 0).unwrap()).unwrap();
+    let (min, max) = lit.get_world_bounding_box();
+    assert!((min.z - -1.0).abs() < 1e-4 && (max.z - 5.0).abs() < 1e-4);
+    assert_eq!(lit.get_color(), Vector3::new(1.0, 1.0, 1.0));
+    assert!(lit.get_flag(Flag::Enabled));
+
+    let dark = shapes.get_cube(wall.cube_at(1, 0).unwrap()).unwrap();
+    assert_eq!(dark.get_color(), Vector3::new(0.0, 0.0, 0.0));
+    assert!(!dark.get_flag(Flag::Enabled));
+
+    // stopping restores the resting cubes
+    wall.stop(&mut shapes);
+    let dark = shapes.get_cube(wall.cube_at(1, 0).unwrap()).unwrap();
+    assert_eq!(dark.get_color(), Vector3::new(0.5, 0.5, 0.5));
+    assert!(dark.get_flag(Flag::Enabled));
+    assert_eq!(half_size(&shapes, &wall, 1, 0), 1.0);
+}
+
+#[test]
+fn stop_and_ping_pong_modes() {
+    let dir = write_frames("wall_modes", 3);
+    let mut shapes = ShapeManager::new();
+    let mut wall = VideoWall::new(
+        Video::load(dir.to_str().unwrap(), 4, 3),
+        10.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        2.0,
+        (1.0, 1.0, 1.0).into(),
+        &mut shapes,
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    wall.set_playback_mode(PlaybackMode::Stop);
+    wall.play();
+    wall.update(Duration::from_millis(400), &mut shapes);
+    assert!(!wall.is_playing());
+    assert_eq!(wall.time(), 0.0);
+    assert_eq!(half_size(&shapes, &wall, 1, 0), 1.0);
+
+    // 0.3s long, so 0.4s in is 0.1s back from the end
+    wall.set_playback_mode(PlaybackMode::PingPong);
+    wall.play();
+    wall.update(Duration::from_millis(400), &mut shapes);
+    assert!((wall.time() - 0.2).abs() < 1e-5);
+    wall.update(Duration::from_millis(150), &mut shapes);
+    assert!((wall.time() - 0.05).abs() < 1e-5);
+    assert_eq!(half_size(&shapes, &wall, 0, 0), 1.0);
+    // and forwards again after reaching the start
+    wall.update(Duration::from_millis(100), &mut shapes);
+    assert!((wall.time() - 0.05).abs() < 1e-5);
+    wall.update(Duration::from_millis(100), &mut shapes);
+    assert!((wall.time() - 0.15).abs() < 1e-5);
+    assert!(wall.is_playing());
+}
+
+#[test]
+fn empty_videos_show_a_placeholder() {
+    let dir = std::env::temp_dir().join(format!("wall_empty_{}", std::process::id()));
+    let mut shapes = ShapeManager::new();
+    let mut wall = VideoWall::from_dir(
+        dir.to_str().unwrap(),
+        (4, 3),
+        10.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        2.0,
+        (1.0, 1.0, 1.0).into(),
+        &mut shapes,
+    );
+    assert_eq!(wall.frame_count(), 0);
+    assert_eq!(wall.duration(), 0.0);
+
+    assert_eq!(color(&shapes, &wall, 0, 0), Vector3::new(1.0, 0.0, 1.0));
+    assert_eq!(color(&shapes, &wall, 1, 0), Vector3::new(0.0, 0.0, 0.0));
+
+    for mode in [PlaybackMode::Stop, PlaybackMode::Loop, PlaybackMode::PingPong, PlaybackMode::HoldLast] {
+        wall.set_playback_mode(mode);
+        wall.play();
+        wall.update(Duration::from_millis(100), &mut shapes);
+        wall.seek(1.0);
+        wall.apply_frame(&mut shapes);
+        assert!(!wall.is_playing());
+    }
+    wall.stop(&mut shapes);
+    assert_eq!(color(&shapes, &wall, 0, 0), Vector3::new(1.0, 0.0, 1.0));
+    assert_eq!(half_size(&shapes, &wall, 0, 0), 1.0);
+}
+
+#[test]
+fn wall_follows_removed_cubes() {
+    let mut shapes = ShapeManager::new();
+    shapes.new_cube((0.0, 0.0, 0.0).into(), (1.0, 1.0, 1.0).into(), (1.0, 1.0, 1.0).into());
+    let mut wall = VideoWall::new(
+        Video::from_frames(2, 1, vec![]),
+        10.0,
+        Vector3::new(0.0, 10.0, 0.0),
+        2.0,
+        (1.0, 1.0, 1.0).into(),
+        &mut shapes,
+    );
+    assert_eq!(wall.cubes(), &[1, 2]);
+
+    let removed = shapes.remove_shape(0).unwrap();
+    wall.cube_removed(removed.get_index());
+    assert_eq!(wall.cubes(), &[0, 1]);
+    let cube = shapes.get_cube(wall.cube_at(1, 0).unwrap()).unwrap();
+    let (min, max) = cube.get_world_bounding_box();
+    assert_eq!((min + max) / 2.0, Vector3::new(2.0, 10.0, 0.0));
+    // putting it back, as undoing the removal does
+    shapes.insert_shape(0, removed);
+    wall.cube_inserted(0);
+    assert_eq!(wall.cubes(), &[1, 2]);
+    let cube = shapes.get_cube(wall.cube_at(1, 0).unwrap()).unwrap();
+    let (min, max) = cube.get_world_bounding_box();
+    assert_eq!((min + max) / 2.0, Vector3::new(2.0, 10.0, 0.0));
+}
+
+#[test]
+fn rotations_about_a_zero_axis_are_dropped() {
+    let dir = write_frames("wall_zero_axis", 1);
+    let mut shapes = ShapeManager::new();
+    let mut wall = VideoWall::new(
+        Video::load(dir.to_str().unwrap(), 4, 3),
+        10.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        2.0,
+        (0.5, 0.5, 0.5).into(),
+        &mut shapes,
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let max_angle = cgmath::Rad(1.0);
+    wall.set_mappings(vec![
+        WallMapping::Rotation {
+            axis: Vector3::new(0.0, 0.0, 0.0),
+            max_angle,
+        },
+        WallMapping::Rotation {
+            axis: Vector3::new(0.0, 2.0, 0.0),
+            max_angle,
+        },
+    ]);
+    assert_eq!(
+        wall.mappings(),
+        &[WallMapping::Rotation {
+            axis: Vector3::new(0.0, 1.0, 0.0),
+            max_angle,
+        }]
+    );
+
+    wall.apply_frame(&mut shapes);
+    let lit = shapes.get_cube(wall.cube_at(0, 0).unwrap()).unwrap();
+    let rotation = lit.get_rotation();
+    assert!(rotation.s.is_finite() && rotation.v.x.is_finite());
+    assert!((rotation.s - 0.5f32.cos()).abs() < 1e-5);
+}